@@ -0,0 +1,140 @@
+use axum::{
+    Json,
+    extract::Query,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+
+/// Grams per unit.
+const WEIGHT_UNITS: &[(&str, f64)] = &[
+    ("g", 1.0),
+    ("kg", 1000.0),
+    ("oz", 28.349523125),
+    ("lb", 453.59237),
+];
+
+/// Millimetres per unit.
+const LENGTH_UNITS: &[(&str, f64)] = &[
+    ("mm", 1.0),
+    ("cm", 10.0),
+    ("m", 1000.0),
+    ("in", 25.4),
+    ("ft", 304.8),
+];
+
+/// Cubic centimetres per unit.
+const VOLUME_UNITS: &[(&str, f64)] = &[
+    ("cm3", 1.0),
+    ("m3", 1_000_000.0),
+    ("l", 1000.0),
+    ("ml", 1.0),
+    ("in3", 16.387064),
+    ("ft3", 28316.846592),
+];
+
+fn convert(value: f64, from: &str, to: &str, table: &[(&str, f64)]) -> Result<f64, ()> {
+    let factor = |unit: &str| {
+        table
+            .iter()
+            .find(|(name, _)| *name == unit)
+            .map(|(_, factor)| *factor)
+            .ok_or(())
+    };
+
+    Ok(value * factor(from)? / factor(to)?)
+}
+
+#[derive(serde::Deserialize)]
+pub struct ConvertParameters {
+    value: f64,
+    from: String,
+    to: String,
+}
+
+#[derive(serde::Serialize)]
+pub struct ConvertResponse {
+    result: f64,
+    unit: String,
+}
+
+fn convert_response(params: ConvertParameters, table: &[(&str, f64)]) -> Response {
+    match convert(params.value, &params.from, &params.to, table) {
+        Ok(result) => Json(ConvertResponse {
+            result,
+            unit: params.to,
+        })
+        .into_response(),
+        Err(()) => (StatusCode::BAD_REQUEST, "Unknown unit").into_response(),
+    }
+}
+
+pub async fn convert_weight(params: Query<ConvertParameters>) -> impl IntoResponse {
+    let params = params.0;
+    if !(params.value.is_finite() && params.value > 0.0) {
+        return (StatusCode::BAD_REQUEST, "Value must be positive").into_response();
+    }
+
+    convert_response(params, WEIGHT_UNITS)
+}
+
+pub async fn convert_length(params: Query<ConvertParameters>) -> impl IntoResponse {
+    convert_response(params.0, LENGTH_UNITS)
+}
+
+pub async fn convert_volume(params: Query<ConvertParameters>) -> impl IntoResponse {
+    convert_response(params.0, VOLUME_UNITS)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_approx_eq(actual: f64, expected: f64) {
+        assert!(
+            (actual - expected).abs() <= f64::EPSILON * expected.abs() * 4.0,
+            "{actual} != {expected}"
+        );
+    }
+
+    #[test]
+    fn converts_between_all_weight_units() {
+        let cases = [
+            ("g", "kg", 0.001),
+            ("g", "oz", 0.035273961949580414),
+            ("g", "lb", 0.002204622621848776),
+            ("kg", "g", 1000.0),
+            ("kg", "oz", 35.27396194958041),
+            ("kg", "lb", 2.2046226218487757),
+            ("oz", "g", 28.349523125),
+            ("oz", "kg", 0.028349523125),
+            ("oz", "lb", 0.0625),
+            ("lb", "g", 453.59237),
+            ("lb", "kg", 0.45359237),
+            ("lb", "oz", 16.0),
+        ];
+
+        for (from, to, expected) in cases {
+            let actual = convert(1.0, from, to, WEIGHT_UNITS).unwrap();
+            assert_approx_eq(actual, expected);
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_weight_units() {
+        assert!(convert(1.0, "g", "stone", WEIGHT_UNITS).is_err());
+        assert!(convert(1.0, "ton", "kg", WEIGHT_UNITS).is_err());
+    }
+
+    #[tokio::test]
+    async fn rejects_non_finite_weights() {
+        for value in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            let params = Query(ConvertParameters {
+                value,
+                from: "g".to_string(),
+                to: "kg".to_string(),
+            });
+            let response = convert_weight(params).await.into_response();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{value}");
+        }
+    }
+}
@@ -1,36 +1,932 @@
-use std::str::FromStr;
+use std::{
+    cmp::Ordering,
+    collections::{BTreeMap, HashMap, HashSet, VecDeque},
+    convert::Infallible,
+    net::{IpAddr, SocketAddr},
+    str::FromStr,
+    time::{Duration, Instant},
+};
 
 use axum::{
-    http::{HeaderMap, StatusCode},
+    Json,
+    body::{Body, Bytes},
+    extract::State,
+    http::{HeaderMap, HeaderValue, StatusCode, header},
     response::{IntoResponse, Response},
 };
+use axum_extra::extract::Query;
+use cargo_manifest::{Edition, MaybeInherited, StringOrBool};
+use chrono::NaiveDate;
+use quick_xml::{
+    Writer,
+    events::{BytesDecl, BytesText, Event},
+};
+use sha2::{Digest, Sha256};
+use tokio_stream::wrappers::ReceiverStream;
 use toml::{Table, Value};
 
-#[derive(serde::Serialize, Debug)]
+use crate::AppState;
+
+/// Read access to a single order entry, whichever format the manifest was written in.
+trait OrderValue {
+    fn field(&self, key: &str) -> Option<&Self>;
+    fn str_value(&self) -> Option<&str>;
+    fn int_value(&self) -> Option<i64>;
+    fn float_value(&self) -> Option<f64>;
+    fn bool_value(&self) -> Option<bool>;
+    fn list_value(&self) -> Option<Vec<&Self>>;
+    fn map_value(&self) -> Option<Vec<(&str, &Self)>>;
+
+    /// Parses an optional field, failing only when it is present but unusable.
+    fn optional<T>(
+        &self,
+        key: &str,
+        parse: impl FnOnce(&Self) -> Option<T>,
+    ) -> Result<Option<T>, String> {
+        self.field(key)
+            .map(|value| parse(value).ok_or_else(|| format!("Invalid {key}")))
+            .transpose()
+    }
+}
+
+impl OrderValue for Value {
+    fn field(&self, key: &str) -> Option<&Self> {
+        self.get(key)
+    }
+
+    fn str_value(&self) -> Option<&str> {
+        self.as_str()
+    }
+
+    fn int_value(&self) -> Option<i64> {
+        self.as_integer()
+    }
+
+    fn float_value(&self) -> Option<f64> {
+        self.as_float()
+            .or_else(|| self.as_integer().map(|i| i as f64))
+    }
+
+    fn bool_value(&self) -> Option<bool> {
+        self.as_bool()
+    }
+
+    fn list_value(&self) -> Option<Vec<&Self>> {
+        self.as_array().map(|values| values.iter().collect())
+    }
+
+    fn map_value(&self) -> Option<Vec<(&str, &Self)>> {
+        self.as_table()
+            .map(|table| table.iter().map(|(k, v)| (k.as_str(), v)).collect())
+    }
+}
+
+impl OrderValue for serde_yaml::Value {
+    fn field(&self, key: &str) -> Option<&Self> {
+        self.get(key)
+    }
+
+    fn str_value(&self) -> Option<&str> {
+        self.as_str()
+    }
+
+    fn int_value(&self) -> Option<i64> {
+        self.as_i64()
+    }
+
+    fn float_value(&self) -> Option<f64> {
+        self.as_f64()
+    }
+
+    fn bool_value(&self) -> Option<bool> {
+        self.as_bool()
+    }
+
+    fn list_value(&self) -> Option<Vec<&Self>> {
+        self.as_sequence().map(|values| values.iter().collect())
+    }
+
+    fn map_value(&self) -> Option<Vec<(&str, &Self)>> {
+        self.as_mapping().and_then(|mapping| {
+            mapping
+                .iter()
+                .map(|(k, v)| k.as_str().map(|k| (k, v)))
+                .collect()
+        })
+    }
+}
+
+impl OrderValue for serde_json::Value {
+    fn field(&self, key: &str) -> Option<&Self> {
+        self.get(key)
+    }
+
+    fn str_value(&self) -> Option<&str> {
+        self.as_str()
+    }
+
+    fn int_value(&self) -> Option<i64> {
+        self.as_i64()
+    }
+
+    fn float_value(&self) -> Option<f64> {
+        self.as_f64()
+    }
+
+    fn bool_value(&self) -> Option<bool> {
+        self.as_bool()
+    }
+
+    fn list_value(&self) -> Option<Vec<&Self>> {
+        self.as_array().map(|values| values.iter().collect())
+    }
+
+    fn map_value(&self) -> Option<Vec<(&str, &Self)>> {
+        self.as_object()
+            .map(|object| object.iter().map(|(k, v)| (k.as_str(), v)).collect())
+    }
+}
+
+fn parse_date<V: OrderValue>(value: &V) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(value.str_value()?, "%Y-%m-%d").ok()
+}
+
+fn parse_age_range<V: OrderValue>(value: &V) -> Option<[u8; 2]> {
+    let ages = value
+        .list_value()?
+        .into_iter()
+        .map(|v| v.int_value().and_then(|age| u8::try_from(age).ok()))
+        .collect::<Option<Vec<u8>>>()?;
+
+    match ages[..] {
+        [min, max] if min <= max && max <= 18 => Some([min, max]),
+        _ => None,
+    }
+}
+
+fn parse_composition<V: OrderValue>(value: &V) -> Option<HashMap<String, f64>> {
+    value
+        .map_value()?
+        .into_iter()
+        .map(|(material, pct)| Some((material.to_string(), pct.float_value()?)))
+        .collect()
+}
+
+fn parse_options<V: OrderValue>(value: &V) -> Option<HashMap<String, Vec<String>>> {
+    value
+        .map_value()?
+        .into_iter()
+        .map(|(option, choices)| Some((option.to_string(), parse_string_list(choices)?)))
+        .collect()
+}
+
+fn parse_string_list<V: OrderValue>(value: &V) -> Option<Vec<String>> {
+    value
+        .list_value()?
+        .into_iter()
+        .map(|v| v.str_value().map(str::to_string))
+        .collect()
+}
+
+const ALLERGEN_SEVERITIES: [&str; 3] = ["low", "medium", "high"];
+
+pub const SHIPPING_CLASSES: [&str; 4] = ["standard", "expedited", "overnight", "freight"];
+
+/// How many levels of `components` may be nested below a top-level order.
+const MAX_COMPONENT_DEPTH: u8 = 3;
+
+pub const SALES_CHANNELS: [&str; 4] = ["online", "store", "wholesale", "catalog"];
+
+pub const SUBSCRIPTION_FREQUENCIES: [&str; 3] = ["monthly", "quarterly", "annual"];
+
+const TAX_CATEGORIES: [&str; 3] = ["standard", "reduced", "exempt"];
+
+/// SPI resin codes most kerbside schemes accept: PET, HDPE, LDPE and PP.
+const RECYCLABLE_RESIN_CODES: [u8; 4] = [1, 2, 4, 5];
+
+fn is_recyclable_resin(code: u8) -> bool {
+    RECYCLABLE_RESIN_CODES.contains(&code)
+}
+
+/// Matches `^[A-Z0-9]{6,12}$`.
+fn is_valid_supplier_id(id: &str) -> bool {
+    (6..=12).contains(&id.len())
+        && id
+            .chars()
+            .all(|c| c.is_ascii_uppercase() || c.is_ascii_digit())
+}
+
+/// The CSS named colors, without the `grey` spellings of the `gray` ones.
+const NAMED_COLORS: [(&str, &str); 140] = [
+    ("aliceblue", "#f0f8ff"),
+    ("antiquewhite", "#faebd7"),
+    ("aqua", "#00ffff"),
+    ("aquamarine", "#7fffd4"),
+    ("azure", "#f0ffff"),
+    ("beige", "#f5f5dc"),
+    ("bisque", "#ffe4c4"),
+    ("black", "#000000"),
+    ("blanchedalmond", "#ffebcd"),
+    ("blue", "#0000ff"),
+    ("blueviolet", "#8a2be2"),
+    ("brown", "#a52a2a"),
+    ("burlywood", "#deb887"),
+    ("cadetblue", "#5f9ea0"),
+    ("chartreuse", "#7fff00"),
+    ("chocolate", "#d2691e"),
+    ("coral", "#ff7f50"),
+    ("cornflowerblue", "#6495ed"),
+    ("cornsilk", "#fff8dc"),
+    ("crimson", "#dc143c"),
+    ("cyan", "#00ffff"),
+    ("darkblue", "#00008b"),
+    ("darkcyan", "#008b8b"),
+    ("darkgoldenrod", "#b8860b"),
+    ("darkgray", "#a9a9a9"),
+    ("darkgreen", "#006400"),
+    ("darkkhaki", "#bdb76b"),
+    ("darkmagenta", "#8b008b"),
+    ("darkolivegreen", "#556b2f"),
+    ("darkorange", "#ff8c00"),
+    ("darkorchid", "#9932cc"),
+    ("darkred", "#8b0000"),
+    ("darksalmon", "#e9967a"),
+    ("darkseagreen", "#8fbc8f"),
+    ("darkslateblue", "#483d8b"),
+    ("darkslategray", "#2f4f4f"),
+    ("darkturquoise", "#00ced1"),
+    ("darkviolet", "#9400d3"),
+    ("deeppink", "#ff1493"),
+    ("deepskyblue", "#00bfff"),
+    ("dimgray", "#696969"),
+    ("dodgerblue", "#1e90ff"),
+    ("firebrick", "#b22222"),
+    ("floralwhite", "#fffaf0"),
+    ("forestgreen", "#228b22"),
+    ("fuchsia", "#ff00ff"),
+    ("gainsboro", "#dcdcdc"),
+    ("ghostwhite", "#f8f8ff"),
+    ("gold", "#ffd700"),
+    ("goldenrod", "#daa520"),
+    ("gray", "#808080"),
+    ("green", "#008000"),
+    ("greenyellow", "#adff2f"),
+    ("honeydew", "#f0fff0"),
+    ("hotpink", "#ff69b4"),
+    ("indianred", "#cd5c5c"),
+    ("indigo", "#4b0082"),
+    ("ivory", "#fffff0"),
+    ("khaki", "#f0e68c"),
+    ("lavender", "#e6e6fa"),
+    ("lavenderblush", "#fff0f5"),
+    ("lawngreen", "#7cfc00"),
+    ("lemonchiffon", "#fffacd"),
+    ("lightblue", "#add8e6"),
+    ("lightcoral", "#f08080"),
+    ("lightcyan", "#e0ffff"),
+    ("lightgoldenrodyellow", "#fafad2"),
+    ("lightgray", "#d3d3d3"),
+    ("lightgreen", "#90ee90"),
+    ("lightpink", "#ffb6c1"),
+    ("lightsalmon", "#ffa07a"),
+    ("lightseagreen", "#20b2aa"),
+    ("lightskyblue", "#87cefa"),
+    ("lightslategray", "#778899"),
+    ("lightsteelblue", "#b0c4de"),
+    ("lightyellow", "#ffffe0"),
+    ("lime", "#00ff00"),
+    ("limegreen", "#32cd32"),
+    ("linen", "#faf0e6"),
+    ("magenta", "#ff00ff"),
+    ("maroon", "#800000"),
+    ("mediumaquamarine", "#66cdaa"),
+    ("mediumblue", "#0000cd"),
+    ("mediumorchid", "#ba55d3"),
+    ("mediumpurple", "#9370db"),
+    ("mediumseagreen", "#3cb371"),
+    ("mediumslateblue", "#7b68ee"),
+    ("mediumspringgreen", "#00fa9a"),
+    ("mediumturquoise", "#48d1cc"),
+    ("mediumvioletred", "#c71585"),
+    ("midnightblue", "#191970"),
+    ("mintcream", "#f5fffa"),
+    ("mistyrose", "#ffe4e1"),
+    ("moccasin", "#ffe4b5"),
+    ("navajowhite", "#ffdead"),
+    ("navy", "#000080"),
+    ("oldlace", "#fdf5e6"),
+    ("olive", "#808000"),
+    ("olivedrab", "#6b8e23"),
+    ("orange", "#ffa500"),
+    ("orangered", "#ff4500"),
+    ("orchid", "#da70d6"),
+    ("palegoldenrod", "#eee8aa"),
+    ("palegreen", "#98fb98"),
+    ("paleturquoise", "#afeeee"),
+    ("palevioletred", "#db7093"),
+    ("papayawhip", "#ffefd5"),
+    ("peachpuff", "#ffdab9"),
+    ("peru", "#cd853f"),
+    ("pink", "#ffc0cb"),
+    ("plum", "#dda0dd"),
+    ("powderblue", "#b0e0e6"),
+    ("purple", "#800080"),
+    ("red", "#ff0000"),
+    ("rosybrown", "#bc8f8f"),
+    ("royalblue", "#4169e1"),
+    ("saddlebrown", "#8b4513"),
+    ("salmon", "#fa8072"),
+    ("sandybrown", "#f4a460"),
+    ("seagreen", "#2e8b57"),
+    ("seashell", "#fff5ee"),
+    ("sienna", "#a0522d"),
+    ("silver", "#c0c0c0"),
+    ("skyblue", "#87ceeb"),
+    ("slateblue", "#6a5acd"),
+    ("slategray", "#708090"),
+    ("snow", "#fffafa"),
+    ("springgreen", "#00ff7f"),
+    ("steelblue", "#4682b4"),
+    ("tan", "#d2b48c"),
+    ("teal", "#008080"),
+    ("thistle", "#d8bfd8"),
+    ("tomato", "#ff6347"),
+    ("turquoise", "#40e0d0"),
+    ("violet", "#ee82ee"),
+    ("wheat", "#f5deb3"),
+    ("white", "#ffffff"),
+    ("whitesmoke", "#f5f5f5"),
+    ("yellow", "#ffff00"),
+    ("yellowgreen", "#9acd32"),
+];
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct Rgb {
+    r: u8,
+    g: u8,
+    b: u8,
+}
+
+impl Rgb {
+    /// Parses a `#rrggbb` hex code.
+    fn parse(hex: &str) -> Option<Self> {
+        let digits = hex
+            .strip_prefix('#')
+            .filter(|d| d.len() == 6 && d.bytes().all(|b| b.is_ascii_hexdigit()))?;
+        let channel = |i: usize| u8::from_str_radix(digits.get(i..i + 2)?, 16).ok();
+
+        Some(Self {
+            r: channel(0)?,
+            g: channel(2)?,
+            b: channel(4)?,
+        })
+    }
+}
+
+/// A toy's color as given in the order, with its RGB channels when it is a
+/// hex code or a CSS color name.
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct ToyColor {
+    raw: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    hex: Option<&'static str>,
+    #[serde(flatten)]
+    rgb: Option<Rgb>,
+}
+
+impl ToyColor {
+    fn parse(raw: &str) -> Self {
+        if let Some(rgb) = Rgb::parse(raw) {
+            return Self {
+                raw: raw.to_string(),
+                hex: None,
+                rgb: Some(rgb),
+            };
+        }
+
+        let name = raw.to_ascii_lowercase().replace("grey", "gray");
+        let hex = NAMED_COLORS
+            .iter()
+            .find(|(color, _)| *color == name)
+            .map(|(_, hex)| *hex);
+        Self {
+            raw: raw.to_string(),
+            hex,
+            rgb: hex.and_then(Rgb::parse),
+        }
+    }
+}
+
+/// Matches `^[a-z]{2}-[a-z]+-\d[a-z]?$`, e.g. `us-east-1a`.
+pub fn is_valid_availability_zone(zone: &str) -> bool {
+    let lowercase = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_lowercase());
+    let mut parts = zone.splitn(3, '-');
+    let (Some(region), Some(area), Some(number)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return false;
+    };
+
+    region.len() == 2
+        && lowercase(region)
+        && lowercase(area)
+        && match number.as_bytes() {
+            [digit] => digit.is_ascii_digit(),
+            [digit, letter] => digit.is_ascii_digit() && letter.is_ascii_lowercase(),
+            _ => false,
+        }
+}
+
+const QUANTITY_UNITS: [&str; 5] = ["each", "box", "pack", "set", "dozen"];
+
+/// Problems with an order that were tolerated rather than rejecting the toy.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ToyWarning {
+    InvalidBarcode,
+    UnknownShippingClass,
+    UnknownQuantityUnit,
+    UnknownTaxCategory,
+    InvalidSupplierId,
+    InvalidAvailabilityZone,
+    /// The quantity was below zero before being cast to `u32`.
+    NegativeQuantity,
+}
+
+fn has_valid_check_digit(digits: &[u32]) -> bool {
+    let Some((check, payload)) = digits.split_last() else {
+        return false;
+    };
+    let sum: u32 = payload
+        .iter()
+        .rev()
+        .enumerate()
+        .map(|(i, d)| if i % 2 == 0 { d * 3 } else { *d })
+        .sum();
+
+    (10 - sum % 10) % 10 == *check
+}
+
+/// Expands a zero-suppressed UPC-E code to the equivalent UPC-A digits.
+fn expand_upc_e(digits: &[u32]) -> Option<Vec<u32>> {
+    let &[system, d1, d2, d3, d4, d5, d6, check] = digits else {
+        return None;
+    };
+    if system > 1 {
+        return None;
+    }
+
+    let body = match d6 {
+        0..=2 => [d1, d2, d6, 0, 0, 0, 0, d3, d4, d5],
+        3 => [d1, d2, d3, 0, 0, 0, 0, 0, d4, d5],
+        4 => [d1, d2, d3, d4, 0, 0, 0, 0, 0, d5],
+        _ => [d1, d2, d3, d4, d5, 0, 0, 0, 0, d6],
+    };
+
+    Some([&[system][..], &body, &[check]].concat())
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
+pub struct Barcode {
+    barcode: String,
+    #[serde(rename = "type")]
+    kind: &'static str,
+}
+
+impl Barcode {
+    fn parse(code: &str) -> Option<Self> {
+        let digits = code
+            .chars()
+            .map(|c| c.to_digit(10))
+            .collect::<Option<Vec<u32>>>()?;
+
+        let kind = match digits.len() {
+            13 if has_valid_check_digit(&digits) => "EAN-13",
+            12 if has_valid_check_digit(&digits) => "UPC-A",
+            8 if has_valid_check_digit(&digits) => "EAN-8",
+            8 if expand_upc_e(&digits).is_some_and(|upc_a| has_valid_check_digit(&upc_a)) => {
+                "UPC-E"
+            }
+            _ => return None,
+        };
+
+        Some(Self {
+            barcode: code.to_string(),
+            kind,
+        })
+    }
+}
+
+#[derive(serde::Serialize, Clone, Debug)]
 pub struct ValidToy {
     pub item: String,
     pub quantity: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expiry_date: Option<NaiveDate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub produced_at: Option<NaiveDate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allergens: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub allergen_severity: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub barcode: Option<Barcode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_order_qty: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shipping_class: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quantity_unit: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tax_category: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub supplier_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub hazmat: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub preorder: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub in_stock: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub set_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub set_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub age_range: Option<[u8; 2]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub composition: Option<HashMap<String, f64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub options: Option<HashMap<String, Vec<String>>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sales_channels: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub availability_zones: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subscription: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub discount_pct: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<ToyColor>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retail_price: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub wholesale_price: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recycling_code: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recyclable: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub popularity_rank: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub review_score: Option<f64>,
+    /// 1 is very durable, 5 extremely fragile.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fragility: Option<u8>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub weight_grams: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub carbon_kg_co2e: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lead_time_days: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub components: Option<Vec<ValidToy>>,
+    pub depth: u8,
+    #[serde(skip)]
+    pub warnings: Vec<ToyWarning>,
 }
 
-impl TryFrom<Table> for ValidToy {
-    type Error = String;
+impl ValidToy {
+    fn from_order<V: OrderValue>(order: &V) -> Result<Self, String> {
+        Self::from_order_at_depth(order, 0)
+    }
 
-    fn try_from(value: Table) -> Result<Self, Self::Error> {
-        let quantity = match value.get("quantity") {
-            Some(opt) => match opt {
-                Value::Integer(quantity) => *quantity as u32,
-                _ => return Err("Invalid quantity type".to_string()),
-            },
+    fn from_order_at_depth<V: OrderValue>(order: &V, depth: u8) -> Result<Self, String> {
+        let raw_quantity = match order.field("quantity") {
+            Some(quantity) => quantity.int_value().ok_or("Invalid quantity type")?,
             None => return Err("Missing quantity".to_string()),
         };
-        let item = match value.get("item").to_owned() {
-            Some(item) => match item {
-                Value::String(item) => item.to_string(),
-                _ => return Err("Invalid item type".to_string()),
-            },
+        let quantity = raw_quantity as u32;
+        let item = match order.field("item") {
+            Some(item) => item.str_value().ok_or("Invalid item type")?.to_string(),
             None => return Err("Missing item".to_string()),
         };
-        Ok(ValidToy { quantity, item })
+        let expiry_date = order.optional("expiry_date", parse_date)?;
+        let produced_at = order.optional("produced_at", parse_date)?;
+        let allergens = order.optional("allergens", parse_string_list)?;
+        let allergen_severity = order
+            .optional("allergen_severity", |v| {
+                v.str_value()
+                    .filter(|s| ALLERGEN_SEVERITIES.contains(s))
+                    .map(str::to_string)
+            })?
+            .or_else(|| {
+                allergens
+                    .as_ref()
+                    .filter(|a| !a.is_empty())
+                    .map(|_| "medium".to_string())
+            });
+
+        let hazmat = order.optional("hazmat", OrderValue::bool_value)?;
+        let preorder = order.optional("preorder", OrderValue::bool_value)?;
+        let in_stock = order.optional("in_stock", OrderValue::bool_value)?;
+        let category = order.optional("category", |v| v.str_value().map(str::to_string))?;
+        let set_id = order.optional("set_id", |v| v.str_value().map(str::to_string))?;
+        let set_name = order.optional("set_name", |v| v.str_value().map(str::to_string))?;
+        let age_range = order.optional("age_range", parse_age_range)?;
+        let composition = order.optional("composition", parse_composition)?;
+        let options = order.optional("options", parse_options)?;
+        let subscription = order.optional("subscription", |v| {
+            v.str_value()
+                .filter(|frequency| SUBSCRIPTION_FREQUENCIES.contains(frequency))
+                .map(str::to_string)
+        })?;
+        let sales_channels = order.optional("sales_channels", |v| {
+            parse_string_list(v).filter(|channels| {
+                channels
+                    .iter()
+                    .all(|channel| SALES_CHANNELS.contains(&channel.as_str()))
+            })
+        })?;
+        let lead_time_days = order.optional("lead_time_days", |v| {
+            v.int_value().and_then(|days| u16::try_from(days).ok())
+        })?;
+        let weight_grams = order.optional("weight_grams", |v| {
+            v.float_value().filter(|grams| *grams >= 0.0)
+        })?;
+        let popularity_rank = order.optional("popularity_rank", |v| {
+            v.int_value()
+                .and_then(|rank| u32::try_from(rank).ok())
+                .filter(|rank| *rank >= 1)
+        })?;
+        let recycling_code = order.optional("recycling_code", |v| {
+            v.int_value()
+                .and_then(|code| u8::try_from(code).ok())
+                .filter(|code| (1..=7).contains(code))
+        })?;
+        let review_score = order.optional("review_score", |v| {
+            v.float_value().filter(|score| (0.0..=5.0).contains(score))
+        })?;
+        let fragility = order.optional("fragility", |v| {
+            v.int_value()
+                .and_then(|level| u8::try_from(level).ok())
+                .filter(|level| (1..=5).contains(level))
+        })?;
+        let carbon_kg_co2e =
+            order.optional("carbon_kg_co2e", |v| v.float_value().filter(|kg| *kg > 0.0))?;
+        let discount_pct = order.optional("discount_pct", |v| {
+            v.float_value().filter(|pct| (0.0..=100.0).contains(pct))
+        })?;
+        let color = order.optional("color", |v| v.str_value().map(ToyColor::parse))?;
+        let retail_price = order.optional("retail_price", |v| v.float_value())?;
+        let wholesale_price = order.optional("wholesale_price", |v| v.float_value())?;
+        let min_order_qty = order.optional("min_order_qty", |v| {
+            v.int_value().and_then(|qty| u32::try_from(qty).ok())
+        })?;
+
+        let components = match order.field("components") {
+            None => None,
+            Some(_) if depth >= MAX_COMPONENT_DEPTH => {
+                return Err(format!(
+                    "Components nested deeper than {MAX_COMPONENT_DEPTH} levels"
+                ));
+            }
+            Some(components) => Some(
+                components
+                    .list_value()
+                    .ok_or("Invalid components type")?
+                    .into_iter()
+                    .map(|component| Self::from_order_at_depth(component, depth + 1))
+                    .collect::<Result<Vec<_>, _>>()?,
+            ),
+        };
+
+        let mut warnings = Vec::new();
+        if raw_quantity < 0 {
+            warnings.push(ToyWarning::NegativeQuantity);
+        }
+        let barcode = order
+            .optional("barcode", |v| v.str_value().and_then(Barcode::parse))
+            .unwrap_or_else(|_| {
+                warnings.push(ToyWarning::InvalidBarcode);
+                None
+            });
+        let shipping_class = order
+            .optional("shipping_class", |v| {
+                v.str_value()
+                    .filter(|class| SHIPPING_CLASSES.contains(class))
+                    .map(str::to_string)
+            })
+            .unwrap_or_else(|_| {
+                warnings.push(ToyWarning::UnknownShippingClass);
+                Some("standard".to_string())
+            });
+        let supplier_id = order
+            .optional("supplier_id", |v| {
+                v.str_value()
+                    .filter(|id| is_valid_supplier_id(id))
+                    .map(str::to_string)
+            })
+            .unwrap_or_else(|_| {
+                warnings.push(ToyWarning::InvalidSupplierId);
+                None
+            });
+        let availability_zones = order
+            .optional("availability_zones", |v| {
+                parse_string_list(v)
+                    .filter(|zones| zones.iter().all(|zone| is_valid_availability_zone(zone)))
+            })
+            .unwrap_or_else(|_| {
+                warnings.push(ToyWarning::InvalidAvailabilityZone);
+                None
+            });
+        let tax_category = order
+            .optional("tax_category", |v| {
+                v.str_value()
+                    .filter(|category| TAX_CATEGORIES.contains(category))
+                    .map(str::to_string)
+            })
+            .unwrap_or_else(|_| {
+                warnings.push(ToyWarning::UnknownTaxCategory);
+                Some("standard".to_string())
+            });
+        let quantity_unit = order
+            .optional("quantity_unit", |v| {
+                v.str_value()
+                    .filter(|unit| QUANTITY_UNITS.contains(unit))
+                    .map(str::to_string)
+            })
+            .unwrap_or_else(|_| {
+                warnings.push(ToyWarning::UnknownQuantityUnit);
+                Some("each".to_string())
+            });
+
+        Ok(ValidToy {
+            item,
+            quantity,
+            expiry_date,
+            produced_at,
+            allergens,
+            allergen_severity,
+            barcode,
+            min_order_qty,
+            shipping_class,
+            quantity_unit,
+            tax_category,
+            supplier_id,
+            hazmat,
+            preorder,
+            in_stock,
+            category,
+            set_id,
+            set_name,
+            age_range,
+            composition,
+            options,
+            sales_channels,
+            availability_zones,
+            subscription,
+            discount_pct,
+            color,
+            retail_price,
+            wholesale_price,
+            recycling_code,
+            recyclable: recycling_code.map(is_recyclable_resin),
+            popularity_rank,
+            review_score,
+            fragility,
+            weight_grams,
+            carbon_kg_co2e,
+            lead_time_days,
+            components,
+            depth,
+            warnings,
+        })
+    }
+
+    /// Every toy in this assembly that isn't itself made of components.
+    pub fn leaves(&self) -> Vec<&ValidToy> {
+        match &self.components {
+            None => vec![self],
+            Some(components) => components.iter().flat_map(ValidToy::leaves).collect(),
+        }
+    }
+
+    /// A toy only belongs to a set when both its id and name are given.
+    pub fn set(&self) -> Option<(&str, &str)> {
+        Some((self.set_id.as_deref()?, self.set_name.as_deref()?))
+    }
+
+    pub fn sold_through(&self, channel: &str) -> bool {
+        self.sales_channels
+            .as_ref()
+            .is_some_and(|channels| channels.iter().any(|c| c == channel))
+    }
+
+    /// Highest review score first, then by name.
+    pub fn cmp_by_review_score(a: &Self, b: &Self) -> Ordering {
+        let score = |t: &Self| t.review_score.unwrap_or(f64::NEG_INFINITY);
+        score(b)
+            .total_cmp(&score(a))
+            .then_with(|| a.item.cmp(&b.item))
+    }
+
+    /// Toys without availability zones are available everywhere.
+    pub fn is_available_in(&self, zone: &str) -> bool {
+        self.availability_zones
+            .as_ref()
+            .is_none_or(|zones| zones.iter().any(|z| z == zone))
+    }
+
+    fn is_online_only(&self) -> bool {
+        self.sales_channels
+            .as_ref()
+            .is_some_and(|channels| channels.iter().all(|c| c == "online") && !channels.is_empty())
+    }
+
+    /// Toys without an explicit shipping class ship as standard.
+    pub fn shipping_class(&self) -> &str {
+        self.shipping_class.as_deref().unwrap_or("standard")
+    }
+
+    /// Toys without an explicit tax category are taxed at the standard rate.
+    pub fn tax_category(&self) -> &str {
+        self.tax_category.as_deref().unwrap_or("standard")
+    }
+
+    /// Material percentages must add up to 100, give or take rounding.
+    fn has_valid_composition(&self) -> bool {
+        self.composition
+            .as_ref()
+            .is_none_or(|c| (99.0..=101.0).contains(&c.values().sum::<f64>()))
+    }
+
+    /// Prices must be positive, and nobody sells below what they paid.
+    fn has_valid_pricing(&self) -> bool {
+        let positive = |price: Option<f64>| price.is_none_or(|p| p > 0.0);
+        positive(self.retail_price)
+            && positive(self.wholesale_price)
+            && self
+                .retail_price
+                .zip(self.wholesale_price)
+                .is_none_or(|(retail, wholesale)| wholesale <= retail)
+    }
+
+    /// Gross margin as a percentage of the retail price.
+    pub fn margin_pct(&self) -> Option<f64> {
+        let (retail, wholesale) = self.retail_price.zip(self.wholesale_price)?;
+        Some((retail - wholesale) / retail * 100.0)
+    }
+
+    fn is_below_minimum(&self) -> bool {
+        self.min_order_qty.is_some_and(|min| self.quantity < min)
+    }
+
+    fn has_warning(&self, warning: ToyWarning) -> bool {
+        self.warnings.contains(&warning)
+    }
+
+    pub fn is_high_risk(&self) -> bool {
+        self.allergen_severity.as_deref() == Some("high")
+    }
+
+    pub fn suits_age(&self, age: u8) -> bool {
+        self.age_range
+            .is_some_and(|[min, max]| (min..=max).contains(&age))
+    }
+
+    /// Only an explicit `in_stock = false` marks a toy as unavailable.
+    fn is_out_of_stock(&self) -> bool {
+        self.in_stock == Some(false)
+    }
+
+    fn is_preorder(&self) -> bool {
+        self.preorder == Some(true)
+    }
+
+    /// Toys without a lead time are assumed to be in stock.
+    pub fn arrives_by(&self, today: NaiveDate, target: NaiveDate) -> bool {
+        self.lead_time_days.is_none_or(|days| {
+            today
+                .checked_add_days(chrono::Days::new(days.into()))
+                .is_some_and(|arrival| arrival <= target)
+        })
+    }
+
+    fn is_expired(&self, today: NaiveDate) -> bool {
+        self.expiry_date.is_some_and(|expiry| expiry < today)
+    }
+
+    fn age_days(&self, today: NaiveDate) -> Option<i64> {
+        self.produced_at
+            .map(|produced| (today - produced).num_days())
+    }
+}
+
+impl TryFrom<Table> for ValidToy {
+    type Error = String;
+
+    fn try_from(value: Table) -> Result<Self, Self::Error> {
+        Self::from_order(&Value::Table(value))
     }
 }
 
@@ -38,12 +934,7 @@ impl TryFrom<serde_yaml::Value> for ValidToy {
     type Error = String;
 
     fn try_from(value: serde_yaml::Value) -> Result<Self, Self::Error> {
-        let item = value["item"].as_str().ok_or("Invalid item")?;
-        let quantity = value["quantity"].as_i64().ok_or("Invalid quantity")?;
-        Ok(Self {
-            item: item.to_string(),
-            quantity: quantity as u32,
-        })
+        Self::from_order(&value)
     }
 }
 
@@ -51,18 +942,19 @@ impl TryFrom<serde_json::Value> for ValidToy {
     type Error = String;
 
     fn try_from(value: serde_json::Value) -> Result<Self, Self::Error> {
-        let item = value["item"].as_str().ok_or("Invalid item")?;
-        let quantity = value["quantity"].as_i64().ok_or("Invalid quantity")?;
-        Ok(Self {
-            item: item.to_string(),
-            quantity: quantity as u32,
-        })
+        Self::from_order(&value)
     }
 }
 
 impl std::fmt::Display for ValidToy {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "{}: {}", &self.item, self.quantity)
+        write!(f, "{}: {}", &self.item, self.quantity)?;
+        match self.quantity_unit.as_deref() {
+            None | Some("each") => Ok(()),
+            Some(unit) if self.quantity == 1 => write!(f, " {unit}"),
+            Some("box") => write!(f, " boxes"),
+            Some(unit) => write!(f, " {unit}s"),
+        }
     }
 }
 
@@ -89,19 +981,58 @@ enum ManifestParseError {
     InvalidContentType,
     #[error("Invalid manifest")]
     InvalidManifest,
+    #[error("Duplicate binary target name: {0}")]
+    DuplicateBinaryTarget(String),
+    #[error("Too few keywords: got {got}, minimum is {min}")]
+    TooFewKeywords { got: usize, min: usize },
+    #[error("Too many keywords: got {got}, maximum is {max}")]
+    TooManyKeywords { got: usize, max: usize },
     #[error("Magic keyword not provided")]
     MissingMagicKeyword,
+    #[error("Missing required keyword: {0}")]
+    MissingRequiredKeyword(String),
+    #[error("Forbidden keyword: {0}")]
+    ForbiddenKeyword(String),
+    #[error("Unknown keywords: {}", .0.join(","))]
+    UnknownKeywords(Vec<String>),
     #[error("")]
     MissingOrders,
+    #[error("Duplicate order: {0}")]
+    DuplicateOrder(String),
+    #[error("Unknown validation rule: {0}")]
+    UnknownRule(String),
+    #[error("Dangerous goods not permitted")]
+    DangerousGoods,
+    #[error("Workspace manifests are not supported; provide a package manifest")]
+    WorkspaceManifest,
+    #[error("Invalid version: {0}")]
+    InvalidVersion(String),
+    #[error("Edition too old: manifest uses {found}, minimum required is {minimum}")]
+    EditionTooOld { found: String, minimum: String },
+    #[error("MSRV too old: manifest requires {found}, minimum is {minimum}")]
+    RustVersionTooOld { found: String, minimum: String },
 }
 
 impl IntoResponse for ManifestParseError {
     fn into_response(self) -> Response {
         let status_code = match self {
             ManifestParseError::InvalidContentType => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ManifestParseError::TooFewKeywords { .. } => StatusCode::BAD_REQUEST,
+            ManifestParseError::TooManyKeywords { .. } => StatusCode::BAD_REQUEST,
             ManifestParseError::MissingMagicKeyword => StatusCode::BAD_REQUEST,
+            ManifestParseError::MissingRequiredKeyword(_) => StatusCode::BAD_REQUEST,
+            ManifestParseError::ForbiddenKeyword(_) => StatusCode::BAD_REQUEST,
+            ManifestParseError::UnknownKeywords(_) => StatusCode::BAD_REQUEST,
             ManifestParseError::InvalidManifest => StatusCode::BAD_REQUEST,
+            ManifestParseError::DuplicateBinaryTarget(_) => StatusCode::BAD_REQUEST,
             ManifestParseError::MissingOrders => StatusCode::NO_CONTENT,
+            ManifestParseError::DuplicateOrder(_) => StatusCode::BAD_REQUEST,
+            ManifestParseError::UnknownRule(_) => StatusCode::BAD_REQUEST,
+            ManifestParseError::DangerousGoods => StatusCode::BAD_REQUEST,
+            ManifestParseError::WorkspaceManifest => StatusCode::UNPROCESSABLE_ENTITY,
+            ManifestParseError::InvalidVersion(_) => StatusCode::BAD_REQUEST,
+            ManifestParseError::EditionTooOld { .. } => StatusCode::BAD_REQUEST,
+            ManifestParseError::RustVersionTooOld { .. } => StatusCode::BAD_REQUEST,
         };
 
         (status_code, self.to_string()).into_response()
@@ -109,72 +1040,2315 @@ impl IntoResponse for ManifestParseError {
 }
 
 macro_rules! parse_manifest {
-    ($ns: ident, $body: expr) => {{
+    ($ns: ident, $body: expr, $params: expr) => {{
         let raw =
             $ns::from_str::<$ns::Value>(&$body).map_err(|_| ManifestParseError::InvalidManifest)?;
         let toml_string = toml::to_string(&raw).map_err(|_| ManifestParseError::InvalidManifest)?;
-        cargo_manifest::Manifest::from_str(&toml_string)
+        let cargo_manifest = cargo_manifest::Manifest::from_str(&toml_string)
             .map_err(|_| ManifestParseError::InvalidManifest)?;
+        if cargo_manifest.package.is_none() {
+            return Err(ManifestParseError::WorkspaceManifest);
+        }
 
-        let manifest = $ns::from_value::<Manifest<$ns::Value>>(raw).unwrap();
-        parse_manifest(manifest)
+        let manifest = $ns::from_value::<Manifest<$ns::Value>>(raw)
+            .map_err(|_| ManifestParseError::InvalidManifest)?;
+        parse_manifest(manifest, $params)
     }};
 }
 
-fn parse_toml(body: String) -> Result<String, ManifestParseError> {
-    cargo_manifest::Manifest::from_str(&body).map_err(|_| ManifestParseError::InvalidManifest)?;
-    let package_manifest = toml::from_str::<Manifest<Table>>(&body).unwrap();
-    parse_manifest(package_manifest)
+#[derive(serde::Deserialize, Default)]
+pub struct ManifestParameters {
+    #[serde(default)]
+    exclude_expired: bool,
+    #[serde(default)]
+    include_profiles: bool,
+    #[serde(default)]
+    include_targets: bool,
+    #[serde(default)]
+    include_deps: bool,
+    #[serde(default)]
+    feature_graph: bool,
+    #[serde(default)]
+    include_target_deps: bool,
+    #[serde(default)]
+    return_metadata: bool,
+    #[serde(default)]
+    block_hazmat: bool,
+    #[serde(default)]
+    exclude_preorders: bool,
+    #[serde(default)]
+    only_in_stock: bool,
+    #[serde(default)]
+    include_tax_summary: bool,
+    #[serde(default)]
+    sort_authors: bool,
+    #[serde(default)]
+    dedup: bool,
+    #[serde(default)]
+    count_only: bool,
+    #[serde(default)]
+    strict_keywords: bool,
+    #[serde(default)]
+    strict: bool,
+    child_age: Option<u8>,
+    max_age_days: Option<i64>,
+    min_rust_version: Option<String>,
+    rules: Option<String>,
+    min_orders: Option<usize>,
+    max_orders: Option<usize>,
+    min_keywords: Option<usize>,
+    max_keywords: Option<usize>,
+    group_by: Option<GroupBy>,
+    #[serde(default)]
+    require_keyword: Vec<String>,
+    #[serde(default)]
+    forbidden_keyword: Vec<String>,
 }
 
-fn parse_yaml(body: String) -> Result<String, ManifestParseError> {
-    parse_manifest!(serde_yaml, body)
+#[derive(Clone, Copy, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum GroupBy {
+    Category,
 }
 
-fn parse_json(body: String) -> Result<String, ManifestParseError> {
-    parse_manifest!(serde_json, body)
+#[derive(Default)]
+enum ResponseFormat {
+    #[default]
+    Text,
+    Json,
+    Xml,
+    /// Just the number of valid orders.
+    Count,
 }
 
-fn parse_manifest<T>(manifest: Manifest<T>) -> Result<String, ManifestParseError>
-where
-    ValidToy: TryFrom<T>,
-{
-    match manifest.package.keywords {
-        None => return Err(ManifestParseError::MissingMagicKeyword),
-        Some(k) if !k.contains(&String::from("Christmas 2024")) => {
-            return Err(ManifestParseError::MissingMagicKeyword);
+impl ResponseFormat {
+    fn from_accept(headers: &HeaderMap) -> Self {
+        match headers.get(header::ACCEPT).and_then(|h| h.to_str().ok()) {
+            Some(accept) if accept.contains("application/json") => ResponseFormat::Json,
+            Some(accept) if accept.contains("application/xml") => ResponseFormat::Xml,
+            _ => ResponseFormat::Text,
         }
-        _ => {}
-    };
+    }
+}
 
-    let Some(orders) = manifest.package.metadata.and_then(|m| m.orders) else {
-        return Err(ManifestParseError::MissingOrders);
-    };
+pub struct ManifestResult {
+    toys: Vec<ValidToy>,
+    headers: HeaderMap,
+    /// Top-level fields added alongside `orders` in JSON responses.
+    extra: serde_json::Map<String, serde_json::Value>,
+    format: ResponseFormat,
+    group_by: Option<GroupBy>,
+}
+
+fn text_by_category(toys: &[ValidToy]) -> String {
+    let mut categories = BTreeMap::<&str, Vec<&ValidToy>>::new();
+    let mut uncategorized = Vec::new();
+    for toy in toys {
+        match &toy.category {
+            Some(category) => categories.entry(category).or_default().push(toy),
+            None => uncategorized.push(toy),
+        }
+    }
 
-    let toys = orders
+    categories
         .into_iter()
-        .filter_map(|o| ValidToy::try_from(o).ok())
-        .map(|t| t.to_string())
-        .collect::<Vec<String>>()
-        .join("\n");
+        .chain((!uncategorized.is_empty()).then_some(("Uncategorized", uncategorized)))
+        .map(|(category, toys)| {
+            let lines = toys.iter().map(|t| t.to_string()).collect::<Vec<_>>();
+            format!("## {category}\n{}", lines.join("\n"))
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
 
-    if toys.is_empty() {
-        Err(ManifestParseError::MissingOrders)
-    } else {
-        Ok(toys)
+const TEXT_STREAM_BUFFER: usize = 32;
+
+/// Sends one chunk per toy line, so clients can start reading long listings
+/// before the last line is rendered.
+fn stream_toy_lines(toys: Vec<ValidToy>) -> Body {
+    let (tx, rx) = tokio::sync::mpsc::channel(TEXT_STREAM_BUFFER);
+    tokio::spawn(async move {
+        for (i, toy) in toys.iter().enumerate() {
+            let line = match i {
+                0 => toy.to_string(),
+                _ => format!("\n{toy}"),
+            };
+            if tx
+                .send(Ok::<_, Infallible>(Bytes::from(line)))
+                .await
+                .is_err()
+            {
+                break;
+            }
+        }
+    });
+
+    Body::from_stream(ReceiverStream::new(rx))
+}
+
+fn orders_xml(toys: &[ValidToy]) -> std::io::Result<Vec<u8>> {
+    let mut writer = Writer::new(Vec::new());
+    writer.write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+    writer
+        .create_element("orders")
+        .write_inner_content(|writer| {
+            for toy in toys {
+                writer
+                    .create_element("order")
+                    .write_inner_content(|writer| {
+                        writer
+                            .create_element("item")
+                            .write_text_content(BytesText::new(&toy.item))?;
+                        writer
+                            .create_element("quantity")
+                            .write_text_content(BytesText::new(&toy.quantity.to_string()))?;
+                        Ok(())
+                    })?;
+            }
+            Ok(())
+        })?;
+
+    Ok(writer.into_inner())
+}
+
+impl IntoResponse for ManifestResult {
+    fn into_response(self) -> Response {
+        match self.format {
+            ResponseFormat::Text => {
+                let mut headers = self.headers;
+                let total_weight = self
+                    .extra
+                    .get("total_weight_grams")
+                    .and_then(|w| w.as_f64());
+                if let Some(total_weight) = total_weight {
+                    if let Ok(value) = HeaderValue::from_str(&total_weight.to_string()) {
+                        headers.insert("X-Total-Weight-Grams", value);
+                    }
+                }
+
+                headers.insert(
+                    header::CONTENT_TYPE,
+                    HeaderValue::from_static("text/plain; charset=utf-8"),
+                );
+                let body = match self.group_by {
+                    Some(GroupBy::Category) => Body::from(text_by_category(&self.toys)),
+                    None => stream_toy_lines(self.toys),
+                };
+
+                (headers, body).into_response()
+            }
+            ResponseFormat::Json => {
+                let mut body = self.extra;
+                body.insert("orders".to_string(), serde_json::json!(self.toys));
+
+                (self.headers, Json(body)).into_response()
+            }
+            ResponseFormat::Count => (self.headers, self.toys.len().to_string()).into_response(),
+            ResponseFormat::Xml => match orders_xml(&self.toys) {
+                Ok(xml) => (
+                    self.headers,
+                    [(header::CONTENT_TYPE, "application/xml; charset=utf-8")],
+                    xml,
+                )
+                    .into_response(),
+                Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+            },
+        }
     }
 }
 
-pub async fn manifest(headers: HeaderMap, body: String) -> impl IntoResponse {
-    let Some(content_type) = headers.get("Content-Type").and_then(|h| h.to_str().ok()) else {
-        return (StatusCode::BAD_REQUEST, "Missing Content Type".to_string()).into_response();
+#[derive(serde::Serialize)]
+struct ProfileSettings {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    opt_level: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    debug: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    lto: Option<Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    codegen_units: Option<u16>,
+}
+
+impl From<&cargo_manifest::Profile> for ProfileSettings {
+    fn from(profile: &cargo_manifest::Profile) -> Self {
+        Self {
+            opt_level: profile.opt_level.clone(),
+            debug: profile.debug.clone(),
+            lto: profile.lto.clone(),
+            codegen_units: profile.codegen_units,
+        }
+    }
+}
+
+fn profile_settings(profiles: Option<&cargo_manifest::Profiles>) -> serde_json::Value {
+    let mut settings = serde_json::Map::new();
+    if let Some(profiles) = profiles {
+        for (name, profile) in [("release", &profiles.release), ("dev", &profiles.dev)] {
+            if let Some(profile) = profile {
+                settings.insert(
+                    name.to_string(),
+                    serde_json::json!(ProfileSettings::from(profile)),
+                );
+            }
+        }
+    }
+
+    serde_json::Value::Object(settings)
+}
+
+/// Cargo refuses manifests where two `[[bin]]` targets share a name, since
+/// it would be ambiguous which one `cargo run --bin` refers to.
+fn check_duplicate_binaries(manifest: &cargo_manifest::Manifest) -> Result<(), ManifestParseError> {
+    let mut seen = HashSet::new();
+    for name in manifest.bin.iter().filter_map(|bin| bin.name.as_deref()) {
+        if !seen.insert(name) {
+            return Err(ManifestParseError::DuplicateBinaryTarget(name.to_string()));
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_toml(
+    body: String,
+    params: &ManifestParameters,
+) -> Result<ManifestResult, ManifestParseError> {
+    let cargo_manifest = cargo_manifest::Manifest::from_str(&body)
+        .map_err(|_| ManifestParseError::InvalidManifest)?;
+    if cargo_manifest.package.is_none() {
+        return Err(ManifestParseError::WorkspaceManifest);
+    }
+    check_duplicate_binaries(&cargo_manifest)?;
+    let rust_version = match cargo_manifest.package.as_ref().map(|p| &p.rust_version) {
+        Some(Some(MaybeInherited::Local(version))) => Some(version.as_str()),
+        _ => None,
     };
+    if let Some(minimum) = &params.min_rust_version {
+        check_rust_version(rust_version, minimum)?;
+    }
+    let package_manifest = toml::from_str::<Manifest<Table>>(&body)
+        .map_err(|_| ManifestParseError::InvalidManifest)?;
+    let mut result = parse_manifest(package_manifest, params)?;
 
-    match content_type {
-        "application/toml" => parse_toml(body),
-        "application/yaml" => parse_yaml(body),
-        "application/json" => parse_json(body),
-        _ => Err(ManifestParseError::InvalidContentType),
+    if let Some(version) = rust_version.and_then(|v| HeaderValue::from_str(v).ok()) {
+        result.headers.insert("X-Rust-Version", version);
+    }
+    if let Some(package) = &cargo_manifest.package {
+        insert_build_script_headers(package, &mut result.headers);
+        insert_pattern_headers(package, &mut result.headers);
+        insert_author_headers(package, params.sort_authors, &mut result.headers);
+        insert_repository_headers(package, &mut result.headers);
+    }
+    if params.include_profiles {
+        result.extra.insert(
+            "profiles".to_string(),
+            profile_settings(cargo_manifest.profile.as_ref()),
+        );
+    }
+    if params.include_targets {
+        result.extra.insert(
+            "required_features".to_string(),
+            required_features(&cargo_manifest),
+        );
+    }
+    if params.include_deps {
+        result.extra.insert(
+            "dependencies".to_string(),
+            dependency_versions(
+                cargo_manifest
+                    .dependencies
+                    .as_ref()
+                    .unwrap_or(&Default::default()),
+            ),
+        );
+    }
+    if params.include_target_deps {
+        let target_deps = cargo_manifest
+            .target
+            .iter()
+            .flatten()
+            .map(|(cfg, target)| (cfg.clone(), dependency_versions(&target.dependencies)))
+            .collect();
+        result.extra.insert(
+            "target_deps".to_string(),
+            serde_json::Value::Object(target_deps),
+        );
+    }
+    if params.feature_graph {
+        result.extra.insert(
+            "feature_graph".to_string(),
+            serde_json::json!(cargo_manifest.features.unwrap_or_default()),
+        );
+    }
+
+    Ok(result)
+}
+
+/// `rust-version` may leave out the minor and patch parts, which semver
+/// requires.
+fn parse_rust_version(version: &str) -> Result<semver::Version, ManifestParseError> {
+    let padded = match version.matches('.').count() {
+        0 => format!("{version}.0.0"),
+        1 => format!("{version}.0"),
+        _ => version.to_string(),
+    };
+    semver::Version::parse(&padded)
+        .map_err(|_| ManifestParseError::InvalidVersion(version.to_string()))
+}
+
+/// Manifests without `rust-version` predate MSRVs, so they count as 1.0.0.
+fn check_rust_version(found: Option<&str>, minimum: &str) -> Result<(), ManifestParseError> {
+    let found = found.unwrap_or("1.0.0");
+    if parse_rust_version(found)? < parse_rust_version(minimum)? {
+        return Err(ManifestParseError::RustVersionTooOld {
+            found: found.to_string(),
+            minimum: minimum.to_string(),
+        });
+    }
+
+    Ok(())
+}
+
+fn insert_build_script_headers(package: &cargo_manifest::Package, headers: &mut HeaderMap) {
+    let build = match &package.build {
+        Some(StringOrBool::String(path)) => Some(path.as_str()),
+        Some(StringOrBool::Bool(true)) => Some("build.rs"),
+        Some(StringOrBool::Bool(false)) | None => None,
+    };
+
+    headers.insert(
+        "X-Has-Build-Script",
+        HeaderValue::from_static(if build.is_some() { "true" } else { "false" }),
+    );
+    if let Some(path) = build.filter(|path| *path != "build.rs") {
+        if let Ok(path) = HeaderValue::from_str(path) {
+            headers.insert("X-Build-Script", path);
+        }
+    }
+}
+
+/// Matches `^[^@\s]+@[^@\s]+\.[^@\s]+$`.
+fn is_valid_email(email: &str) -> bool {
+    let Some((local, domain)) = email.split_once('@') else {
+        return false;
+    };
+    let valid_part =
+        |part: &str| !part.is_empty() && !part.chars().any(|c| c == '@' || c.is_whitespace());
+
+    valid_part(local)
+        && valid_part(domain)
+        && domain
+            .char_indices()
+            .any(|(i, c)| c == '.' && i > 0 && i < domain.len() - 1)
+}
+
+/// Splits a `"Name <email>"` author entry into its name and email.
+fn parse_author(author: &str) -> Option<(&str, &str)> {
+    let (name, email) = author.split_once('<')?;
+    Some((name.trim(), email.trim_end().strip_suffix('>')?))
+}
+
+fn insert_author_headers(package: &cargo_manifest::Package, sort: bool, headers: &mut HeaderMap) {
+    let Some(MaybeInherited::Local(authors)) = &package.authors else {
+        return;
+    };
+
+    let mut authors = authors.iter().map(String::as_str).collect::<Vec<_>>();
+    if sort {
+        authors.sort_by_key(|author| parse_author(author).map_or(*author, |(name, _)| name));
+    }
+
+    let (mut valid, mut invalid) = (Vec::new(), Vec::new());
+    for author in authors {
+        match parse_author(author) {
+            Some((_, email)) if is_valid_email(email) => valid.push(email),
+            _ => invalid.push(author),
+        }
+    }
+
+    for (name, entries) in [
+        ("X-Author-Emails", valid),
+        ("X-Invalid-Author-Emails", invalid),
+    ] {
+        if entries.is_empty() {
+            continue;
+        }
+        if let Ok(value) = HeaderValue::from_str(&entries.join(",")) {
+            headers.insert(name, value);
+        }
+    }
+}
+
+/// Splits a repository URL into its host and path. Besides `scheme://` URLs
+/// this accepts the scp-like `git@host:owner/repo` form.
+fn repository_host_and_path(url: &str) -> Option<(&str, &str)> {
+    let (authority, path) = match url.split_once("://") {
+        Some((_, rest)) => rest.split_at(rest.find('/').unwrap_or(rest.len())),
+        None => url.split_once(':')?,
+    };
+    let host = authority
+        .rsplit_once('@')
+        .map_or(authority, |(_, host)| host);
+    let host = host.split_once(':').map_or(host, |(host, _)| host);
+
+    Some((host, path))
+}
+
+fn repository_platform(host: &str) -> &'static str {
+    match host.to_ascii_lowercase().trim_start_matches("www.") {
+        "github.com" => "github",
+        "gitlab.com" => "gitlab",
+        "bitbucket.org" => "bitbucket",
+        "git.sr.ht" | "sr.ht" => "sourcehut",
+        "codeberg.org" => "codeberg",
+        _ => "unknown",
+    }
+}
+
+fn repository_slug(path: &str) -> Option<String> {
+    let mut segments = path.split('/').filter(|s| !s.is_empty());
+    let owner = segments.next()?;
+    let repo = segments.next()?;
+    let repo = repo.strip_suffix(".git").unwrap_or(repo);
+
+    Some(format!("{owner}/{repo}"))
+}
+
+fn insert_repository_headers(package: &cargo_manifest::Package, headers: &mut HeaderMap) {
+    let Some(MaybeInherited::Local(url)) = &package.repository else {
+        return;
+    };
+
+    let (platform, slug) = match repository_host_and_path(url) {
+        Some((host, path)) => match repository_platform(host) {
+            "unknown" => ("unknown", None),
+            platform => (platform, repository_slug(path)),
+        },
+        None => ("unknown", None),
+    };
+    headers.insert("X-Repo-Platform", HeaderValue::from_static(platform));
+    if let Some(slug) = slug.and_then(|slug| HeaderValue::from_str(&slug).ok()) {
+        headers.insert("X-Repo-Slug", slug);
+    }
+}
+
+fn required_features(manifest: &cargo_manifest::Manifest) -> serde_json::Value {
+    let features = manifest
+        .bin
+        .iter()
+        .chain(&manifest.example)
+        .filter(|target| !target.required_features.is_empty())
+        .filter_map(|target| {
+            let name = target.name.clone()?;
+            Some((name, serde_json::json!(target.required_features)))
+        })
+        .collect();
+
+    serde_json::Value::Object(features)
+}
+
+fn dependency_versions(dependencies: &cargo_manifest::DepsSet) -> serde_json::Value {
+    use cargo_manifest::Dependency;
+
+    let dependencies = dependencies
+        .iter()
+        .map(|(name, dependency)| {
+            let value = match dependency {
+                Dependency::Simple(version) => serde_json::json!(version),
+                Dependency::Detailed(detail)
+                    if detail.features.is_none() && detail.default_features.is_none() =>
+                {
+                    serde_json::json!(detail.version)
+                }
+                Dependency::Detailed(detail) => {
+                    let mut value = serde_json::Map::new();
+                    value.insert("version".to_string(), serde_json::json!(detail.version));
+                    if let Some(features) = &detail.features {
+                        value.insert("features".to_string(), serde_json::json!(features));
+                    }
+                    if let Some(default_features) = detail.default_features {
+                        value.insert(
+                            "default_features".to_string(),
+                            serde_json::json!(default_features),
+                        );
+                    }
+                    serde_json::Value::Object(value)
+                }
+                // The version lives in the workspace manifest, which we don't have.
+                Dependency::Inherited(detail) => {
+                    let mut value = serde_json::Map::new();
+                    value.insert("workspace".to_string(), serde_json::json!(true));
+                    if let Some(features) = &detail.features {
+                        value.insert("features".to_string(), serde_json::json!(features));
+                    }
+                    serde_json::Value::Object(value)
+                }
+            };
+            (name.clone(), value)
+        })
+        .collect();
+
+    serde_json::Value::Object(dependencies)
+}
+
+fn parse_yaml(
+    body: String,
+    params: &ManifestParameters,
+) -> Result<ManifestResult, ManifestParseError> {
+    parse_manifest!(serde_yaml, body, params)
+}
+
+fn parse_json(
+    body: String,
+    params: &ManifestParameters,
+) -> Result<ManifestResult, ManifestParseError> {
+    parse_manifest!(serde_json, body, params)
+}
+
+#[derive(Default, serde::Serialize)]
+pub struct TaxSummary {
+    count: usize,
+    total_quantity: u64,
+}
+
+/// Order count and quantity per tax category, including empty categories.
+pub fn tax_breakdown<'a>(
+    toys: impl IntoIterator<Item = &'a ValidToy>,
+) -> BTreeMap<String, TaxSummary> {
+    let mut breakdown = TAX_CATEGORIES
+        .iter()
+        .map(|category| (category.to_string(), TaxSummary::default()))
+        .collect::<BTreeMap<_, _>>();
+    for toy in toys {
+        let summary = breakdown.entry(toy.tax_category().to_string()).or_default();
+        summary.count += 1;
+        summary.total_quantity += u64::from(toy.quantity);
+    }
+
+    breakdown
+}
+
+/// An opt-in check applied to each parsed order; `check` returns why the toy
+/// fails the rule, if it does.
+trait ValidationRule {
+    fn check(&self, toy: &ValidToy) -> Option<String>;
+}
+
+struct NoNegativeQuantity;
+
+impl ValidationRule for NoNegativeQuantity {
+    fn check(&self, toy: &ValidToy) -> Option<String> {
+        toy.has_warning(ToyWarning::NegativeQuantity)
+            .then(|| format!("{} has a negative quantity", toy.item))
+    }
+}
+
+struct MaxQuantity(u32);
+
+impl ValidationRule for MaxQuantity {
+    fn check(&self, toy: &ValidToy) -> Option<String> {
+        (toy.quantity > self.0)
+            .then(|| format!("{} quantity {} exceeds {}", toy.item, toy.quantity, self.0))
+    }
+}
+
+struct MaxItemNameLength(usize);
+
+impl ValidationRule for MaxItemNameLength {
+    fn check(&self, toy: &ValidToy) -> Option<String> {
+        (toy.item.chars().count() > self.0)
+            .then(|| format!("{} is longer than {} characters", toy.item, self.0))
+    }
+}
+
+struct NoHazmat;
+
+impl ValidationRule for NoHazmat {
+    fn check(&self, toy: &ValidToy) -> Option<String> {
+        (toy.hazmat == Some(true)).then(|| format!("{} is hazardous", toy.item))
+    }
+}
+
+fn parse_rule(name: &str) -> Option<Box<dyn ValidationRule>> {
+    match name {
+        "no_negative_qty" => return Some(Box::new(NoNegativeQuantity)),
+        "no_hazmat" => return Some(Box::new(NoHazmat)),
+        _ => {}
+    }
+    if let Some(max) = name.strip_prefix("max_qty_") {
+        return Some(Box::new(MaxQuantity(max.parse().ok()?)));
+    }
+    if let Some(max) = name.strip_prefix("max_name_len_") {
+        return Some(Box::new(MaxItemNameLength(max.parse().ok()?)));
+    }
+
+    None
+}
+
+/// Builds the rules named in `?rules=`, e.g. `max_qty_1000,no_hazmat`.
+fn parse_rules(names: &str) -> Result<Vec<Box<dyn ValidationRule>>, ManifestParseError> {
+    names
+        .split(',')
+        .filter(|name| !name.is_empty())
+        .map(|name| {
+            parse_rule(name).ok_or_else(|| ManifestParseError::UnknownRule(name.to_string()))
+        })
+        .collect()
+}
+
+/// Item names that appear more than once, in the order they first repeat.
+fn duplicate_items(toys: &[ValidToy]) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut duplicates = Vec::new();
+    for toy in toys {
+        if !seen.insert(&toy.item) && !duplicates.contains(&toy.item) {
+            duplicates.push(toy.item.clone());
+        }
+    }
+
+    duplicates
+}
+
+/// Folds repeated items into their first occurrence, summing the quantities.
+fn merge_duplicates(toys: Vec<ValidToy>) -> Vec<ValidToy> {
+    let mut merged: Vec<ValidToy> = Vec::new();
+    for toy in toys {
+        match merged.iter_mut().find(|t| t.item == toy.item) {
+            Some(first) => first.quantity = first.quantity.saturating_add(toy.quantity),
+            None => merged.push(toy),
+        }
+    }
+
+    merged
+}
+
+const MAGIC_KEYWORD: &str = "Christmas 2024";
+
+/// Keywords permitted by `KEYWORD_WHITELIST`, or `None` when any keyword is
+/// allowed. The magic keyword is always permitted, since every manifest
+/// needs it.
+fn keyword_whitelist() -> Option<HashSet<String>> {
+    let whitelist = std::env::var("KEYWORD_WHITELIST").ok()?;
+    let mut allowed = whitelist
+        .split(',')
+        .map(str::trim)
+        .filter(|keyword| !keyword.is_empty())
+        .map(str::to_string)
+        .collect::<HashSet<_>>();
+    allowed.insert(MAGIC_KEYWORD.to_string());
+
+    Some(allowed)
+}
+
+fn parse_manifest<T>(
+    manifest: Manifest<T>,
+    params: &ManifestParameters,
+) -> Result<ManifestResult, ManifestParseError>
+where
+    ValidToy: TryFrom<T>,
+{
+    let rules = parse_rules(params.rules.as_deref().unwrap_or_default())?;
+
+    let keywords = manifest.package.keywords.unwrap_or_default();
+    if let Some(min) = params.min_keywords.filter(|min| keywords.len() < *min) {
+        return Err(ManifestParseError::TooFewKeywords {
+            got: keywords.len(),
+            min,
+        });
+    }
+    if let Some(max) = params.max_keywords.filter(|max| keywords.len() > *max) {
+        return Err(ManifestParseError::TooManyKeywords {
+            got: keywords.len(),
+            max,
+        });
+    }
+    if !keywords.iter().any(|keyword| keyword == MAGIC_KEYWORD) {
+        return Err(ManifestParseError::MissingMagicKeyword);
+    }
+    if let Some(keyword) = params
+        .require_keyword
+        .iter()
+        .find(|keyword| !keywords.contains(keyword))
+    {
+        return Err(ManifestParseError::MissingRequiredKeyword(keyword.clone()));
+    }
+    if let Some(keyword) = params
+        .forbidden_keyword
+        .iter()
+        .find(|keyword| keywords.contains(keyword))
+    {
+        return Err(ManifestParseError::ForbiddenKeyword(keyword.clone()));
+    }
+    let unknown_keywords = keyword_whitelist()
+        .map(|allowed| {
+            keywords
+                .iter()
+                .filter(|keyword| !allowed.contains(*keyword))
+                .cloned()
+                .collect::<Vec<_>>()
+        })
+        .unwrap_or_default();
+    if params.strict_keywords && !unknown_keywords.is_empty() {
+        return Err(ManifestParseError::UnknownKeywords(unknown_keywords));
+    }
+
+    let Some(orders) = manifest.package.metadata.and_then(|m| m.orders) else {
+        return Err(ManifestParseError::MissingOrders);
+    };
+
+    let mut toys = orders
+        .into_iter()
+        .filter_map(|o| ValidToy::try_from(o).ok())
+        .collect::<Vec<ValidToy>>();
+
+    // Rules run in the order given and the first failure skips the toy.
+    let before_rules = toys.len();
+    toys.retain(|toy| rules.iter().all(|rule| rule.check(toy).is_none()));
+    let rule_violations = before_rules - toys.len();
+
+    let duplicates = duplicate_items(&toys);
+    if let Some(duplicate) = duplicates.first().filter(|_| params.strict) {
+        return Err(ManifestParseError::DuplicateOrder(duplicate.clone()));
+    }
+    if params.dedup {
+        toys = merge_duplicates(toys);
+    }
+
+    let contains_hazmat = toys.iter().any(|t| t.hazmat == Some(true));
+    if contains_hazmat && params.block_hazmat {
+        return Err(ManifestParseError::DangerousGoods);
+    }
+
+    let (below_minimum, toys): (Vec<_>, Vec<_>) =
+        toys.into_iter().partition(|t| t.is_below_minimum());
+    let (toys, invalid_composition): (Vec<_>, Vec<_>) =
+        toys.into_iter().partition(|t| t.has_valid_composition());
+    let (mut toys, invalid_pricing): (Vec<_>, Vec<_>) =
+        toys.into_iter().partition(|t| t.has_valid_pricing());
+
+    let today = chrono::Local::now().naive_local().date();
+    let expired_count = toys.iter().filter(|t| t.is_expired(today)).count();
+    if params.exclude_expired {
+        toys.retain(|t| !t.is_expired(today));
+    }
+
+    let preorder_count = toys.iter().filter(|t| t.is_preorder()).count();
+    if params.exclude_preorders {
+        toys.retain(|t| !t.is_preorder());
+    }
+
+    let out_of_stock_count = toys.iter().filter(|t| t.is_out_of_stock()).count();
+    if params.only_in_stock {
+        toys.retain(|t| !t.is_out_of_stock());
+    }
+
+    if let Some(age) = params.child_age {
+        toys.retain(|t| t.suits_age(age));
+    }
+
+    if let Some(max_age) = params.max_age_days {
+        toys.retain(|t| t.age_days(today).is_none_or(|age| age <= max_age));
+    }
+
+    if toys.is_empty() {
+        return Err(ManifestParseError::MissingOrders);
+    }
+
+    let high_risk_count = toys.iter().filter(|t| t.is_high_risk()).count();
+
+    let mut headers = HeaderMap::new();
+    if contains_hazmat {
+        headers.insert("X-Contains-Hazmat", HeaderValue::from_static("true"));
+    }
+    headers.insert("X-Expired-Count", expired_count.into());
+    headers.insert("X-High-Risk-Count", high_risk_count.into());
+    headers.insert("X-Preorder-Count", preorder_count.into());
+    headers.insert("X-Out-Of-Stock", out_of_stock_count.into());
+    headers.insert(
+        "X-Online-Only-Count",
+        toys.iter().filter(|t| t.is_online_only()).count().into(),
+    );
+    if !rules.is_empty() {
+        headers.insert("X-Rule-Violations", rule_violations.into());
+    }
+    if !duplicates.is_empty() {
+        if let Ok(duplicates) = HeaderValue::from_str(&duplicates.join(",")) {
+            headers.insert("X-Duplicate-Orders", duplicates);
+        }
+    }
+    if params.exclude_preorders {
+        headers.insert("X-Excluded-Preorders", preorder_count.into());
+    }
+    if !unknown_keywords.is_empty() {
+        if let Ok(keywords) = HeaderValue::from_str(&unknown_keywords.join(",")) {
+            headers.insert("X-Unknown-Keywords", keywords);
+        }
+    }
+
+    let invalid_barcodes = toys
+        .iter()
+        .filter(|t| t.has_warning(ToyWarning::InvalidBarcode))
+        .count();
+    if invalid_barcodes > 0 {
+        headers.insert("X-Invalid-Barcodes", invalid_barcodes.into());
+    }
+
+    let unknown_shipping_classes = toys
+        .iter()
+        .filter(|t| t.has_warning(ToyWarning::UnknownShippingClass))
+        .count();
+    if unknown_shipping_classes > 0 {
+        headers.insert("X-Unknown-Shipping-Class", unknown_shipping_classes.into());
+    }
+
+    let unknown_quantity_units = toys
+        .iter()
+        .filter(|t| t.has_warning(ToyWarning::UnknownQuantityUnit))
+        .count();
+    if unknown_quantity_units > 0 {
+        headers.insert("X-Unknown-Quantity-Unit", unknown_quantity_units.into());
+    }
+
+    let unknown_tax_categories = toys
+        .iter()
+        .filter(|t| t.has_warning(ToyWarning::UnknownTaxCategory))
+        .count();
+    if unknown_tax_categories > 0 {
+        headers.insert("X-Unknown-Tax-Category", unknown_tax_categories.into());
+    }
+
+    let invalid_supplier_ids = toys
+        .iter()
+        .filter(|t| t.has_warning(ToyWarning::InvalidSupplierId))
+        .count();
+    if invalid_supplier_ids > 0 {
+        headers.insert("X-Invalid-Supplier-IDs", invalid_supplier_ids.into());
+    }
+
+    let invalid_zones = toys
+        .iter()
+        .filter(|t| t.has_warning(ToyWarning::InvalidAvailabilityZone))
+        .count();
+    if invalid_zones > 0 {
+        headers.insert("X-Invalid-AZs", invalid_zones.into());
+    }
+
+    let freight_items = toys
+        .iter()
+        .filter(|t| t.shipping_class() == "freight")
+        .count();
+    if freight_items > 0 {
+        headers.insert("X-Freight-Item-Count", freight_items.into());
+    }
+
+    if let Some(oldest) = toys.iter().filter_map(|t| t.age_days(today)).max() {
+        headers.insert("X-Oldest-Item-Days", oldest.into());
+    }
+
+    if let Some(latest) = toys.iter().filter_map(|t| t.lead_time_days).max() {
+        headers.insert("X-Latest-Lead-Time", latest.into());
+    }
+
+    let discounts = toys
+        .iter()
+        .filter_map(|t| t.discount_pct)
+        .collect::<Vec<_>>();
+    if !discounts.is_empty() {
+        let average = discounts.iter().sum::<f64>() / discounts.len() as f64;
+        if let Ok(average) = HeaderValue::from_str(&format!("{average:.2}")) {
+            headers.insert("X-Average-Discount", average);
+        }
+    }
+
+    let top_rated = toys
+        .iter()
+        .filter(|t| t.review_score.is_some())
+        .min_by(|a, b| ValidToy::cmp_by_review_score(a, b));
+    if let Some(item) = top_rated.and_then(|t| HeaderValue::from_str(&t.item).ok()) {
+        headers.insert("X-Top-Rated-Item", item);
+    }
+
+    let fragility = toys.iter().filter_map(|t| t.fragility).collect::<Vec<_>>();
+    if let Some(max) = fragility.iter().max() {
+        headers.insert("X-Max-Fragility", u16::from(*max).into());
+        let average = fragility.iter().map(|&f| f64::from(f)).sum::<f64>() / fragility.len() as f64;
+        if let Ok(average) = HeaderValue::from_str(&format!("{average:.2}")) {
+            headers.insert("X-Avg-Fragility", average);
+        }
+    }
+
+    let margins = toys
+        .iter()
+        .filter_map(|t| t.margin_pct())
+        .collect::<Vec<_>>();
+    if !margins.is_empty() {
+        let average = margins.iter().sum::<f64>() / margins.len() as f64;
+        if let Ok(average) = HeaderValue::from_str(&format!("{average:.2}")) {
+            headers.insert("X-Average-Margin", average);
+        }
+    }
+
+    for (name, skipped) in [
+        ("X-Below-Minimum-Qty", &below_minimum),
+        ("X-Invalid-Composition", &invalid_composition),
+        ("X-Invalid-Pricing", &invalid_pricing),
+    ] {
+        if skipped.is_empty() {
+            continue;
+        }
+        let items = skipped
+            .iter()
+            .map(|t| t.item.as_str())
+            .collect::<Vec<_>>()
+            .join(",");
+        if let Ok(items) = HeaderValue::from_str(&items) {
+            headers.insert(name, items);
+        }
+    }
+
+    if toys.iter().all(|t| t.carbon_kg_co2e.is_some()) {
+        let total = toys
+            .iter()
+            .filter_map(|t| Some(t.carbon_kg_co2e? * f64::from(t.quantity)))
+            .sum::<f64>();
+        if let Ok(total) = HeaderValue::from_str(&total.to_string()) {
+            headers.insert("X-Carbon-Total", total);
+        }
+    }
+
+    let mut extra = serde_json::Map::new();
+    if params.include_tax_summary {
+        extra.insert(
+            "tax_breakdown".to_string(),
+            serde_json::json!(tax_breakdown(&toys)),
+        );
+    }
+    let weighed = toys
+        .iter()
+        .filter_map(|t| Some(t.weight_grams? * f64::from(t.quantity)))
+        .collect::<Vec<_>>();
+    if !weighed.is_empty() {
+        let coverage_pct = weighed.len() as f64 / toys.len() as f64 * 100.0;
+        extra.insert(
+            "total_weight_grams".to_string(),
+            serde_json::json!(weighed.iter().sum::<f64>()),
+        );
+        extra.insert(
+            "weight_coverage_pct".to_string(),
+            serde_json::json!(coverage_pct),
+        );
+        if weighed.len() < toys.len() {
+            headers.insert("X-Partial-Weight", HeaderValue::from_static("true"));
+        }
+    }
+
+    Ok(ManifestResult {
+        toys,
+        headers,
+        extra,
+        format: ResponseFormat::default(),
+        group_by: params.group_by,
+    })
+}
+
+fn insert_pattern_headers(package: &cargo_manifest::Package, headers: &mut HeaderMap) {
+    let patterns = |field: &Option<MaybeInherited<Vec<String>>>| match field {
+        Some(MaybeInherited::Local(patterns)) => Some(patterns.clone()),
+        _ => None,
+    };
+    let include = patterns(&package.include);
+    let exclude = patterns(&package.exclude);
+
+    for (name, patterns) in [
+        ("X-Include-Patterns", &include),
+        ("X-Exclude-Patterns", &exclude),
+    ] {
+        if let Some(Ok(value)) = patterns
+            .as_ref()
+            .map(|p| HeaderValue::from_str(&p.join(",")))
+        {
+            headers.insert(name, value);
+        }
+    }
+
+    let (Some(include), Some(exclude)) = (include, exclude) else {
+        return;
+    };
+    for pattern in include.iter().filter(|p| exclude.contains(p)) {
+        if let Ok(value) = HeaderValue::from_str(&format!("conflicting-pattern:{pattern}")) {
+            headers.append("X-Warning", value);
+        }
+    }
+}
+
+/// Parses a manifest body of any supported content type as a Cargo manifest.
+fn cargo_manifest_from(
+    content_type: &str,
+    body: &str,
+) -> Result<cargo_manifest::Manifest, ManifestParseError> {
+    let toml_string = match content_type {
+        "application/toml" => Some(body.to_string()),
+        "application/yaml" => serde_yaml::from_str::<serde_yaml::Value>(body)
+            .ok()
+            .and_then(|raw| toml::to_string(&raw).ok()),
+        "application/json" => serde_json::from_str::<serde_json::Value>(body)
+            .ok()
+            .and_then(|raw| toml::to_string(&raw).ok()),
+        _ => return Err(ManifestParseError::InvalidContentType),
+    }
+    .ok_or(ManifestParseError::InvalidManifest)?;
+
+    cargo_manifest::Manifest::from_str(&toml_string)
+        .map_err(|_| ManifestParseError::InvalidManifest)
+}
+
+fn package_metadata(
+    content_type: &str,
+    body: &str,
+) -> Result<Json<serde_json::Value>, ManifestParseError> {
+    let metadata = cargo_manifest_from(content_type, body)?
+        .package
+        .and_then(|package| package.metadata)
+        .map(|metadata| serde_json::json!(metadata))
+        .unwrap_or_else(|| serde_json::json!({}));
+
+    Ok(Json(metadata))
+}
+
+fn content_type(headers: &HeaderMap) -> Option<&str> {
+    headers.get("Content-Type").and_then(|h| h.to_str().ok())
+}
+
+fn is_manifest_content_type(content_type: &str) -> bool {
+    matches!(
+        content_type,
+        "application/toml" | "application/yaml" | "application/json"
+    )
+}
+
+fn parse_body(
+    content_type: &str,
+    body: String,
+    params: &ManifestParameters,
+) -> Result<ManifestResult, ManifestParseError> {
+    match content_type {
+        "application/toml" => parse_toml(body, params),
+        "application/yaml" => parse_yaml(body, params),
+        "application/json" => parse_json(body, params),
+        _ => Err(ManifestParseError::InvalidContentType),
+    }
+}
+
+fn check_order_count(actual: usize, params: &ManifestParameters) -> Result<(), String> {
+    let too_few = params.min_orders.is_some_and(|min| actual < min);
+    let too_many = params.max_orders.is_some_and(|max| actual > max);
+    if !too_few && !too_many {
+        return Ok(());
+    }
+
+    // An unset bound leaves that side of the range open.
+    let min = params.min_orders.unwrap_or(0);
+    let range = match params.max_orders {
+        Some(max) => format!("[{min}, {max}]"),
+        None => format!("[{min}, ∞)"),
+    };
+    Err(format!(
+        "Order count {actual} outside expected range {range}"
+    ))
+}
+
+const MAX_INVENTORY_ITEMS: usize = 10_000;
+
+/// Adds toys to the shared inventory. A toy replaces any earlier entry with
+/// the same item, and the oldest entries go once the inventory is full.
+fn restock(inventory: &mut Vec<ValidToy>, toys: impl IntoIterator<Item = ValidToy>) {
+    for toy in toys {
+        inventory.retain(|t| t.item != toy.item);
+        inventory.push(toy);
+    }
+    let excess = inventory.len().saturating_sub(MAX_INVENTORY_ITEMS);
+    inventory.drain(..excess);
+}
+
+pub async fn manifest(
+    State(state): State<AppState>,
+    Query(params): Query<ManifestParameters>,
+    headers: HeaderMap,
+    body: String,
+) -> impl IntoResponse {
+    let (mut response, parse_time, order_count) =
+        manifest_response(&state, &params, &headers, body);
+    let parse_time_ms = parse_time.as_secs_f64() * 1000.0;
+
+    if let Ok(value) = HeaderValue::from_str(&format!("{parse_time_ms:.2}")) {
+        response.headers_mut().insert("X-Parse-Time-Ms", value);
+    }
+    if let Some(count) = order_count {
+        response.headers_mut().insert("X-Order-Count", count.into());
+    }
+    response
+}
+
+/// Builds the `manifest` response along with the time spent parsing the body
+/// and the order count to report, if any.
+fn manifest_response(
+    state: &AppState,
+    params: &ManifestParameters,
+    headers: &HeaderMap,
+    body: String,
+) -> (Response, Duration, Option<usize>) {
+    let started = Instant::now();
+    let Some(content_type) = content_type(headers) else {
+        return (
+            (StatusCode::BAD_REQUEST, "Missing Content Type".to_string()).into_response(),
+            started.elapsed(),
+            None,
+        );
+    };
+
+    if params.return_metadata {
+        let response = package_metadata(content_type, &body);
+        return (response.into_response(), started.elapsed(), None);
+    }
+
+    let result = parse_body(content_type, body, params);
+    let parse_time = started.elapsed();
+    let result = result.map(|mut result| {
+        result.format = if params.count_only {
+            ResponseFormat::Count
+        } else {
+            ResponseFormat::from_accept(headers)
+        };
+        result
+    });
+    let order_count = match &result {
+        Ok(result) => Some(result.toys.len()),
+        Err(ManifestParseError::MissingOrders) => Some(0),
+        Err(_) => None,
+    };
+
+    let response = match result {
+        Ok(result) => match check_order_count(result.toys.len(), params) {
+            Ok(()) => {
+                restock(
+                    &mut state.inventory.write().unwrap(),
+                    result.toys.iter().cloned(),
+                );
+                result.into_response()
+            }
+            Err(message) => (StatusCode::BAD_REQUEST, message).into_response(),
+        },
+        Err(err) => err.into_response(),
+    };
+    (response, parse_time, order_count)
+}
+
+const FETCH_TIMEOUT: Duration = Duration::from_secs(5);
+const FETCH_MAX_BODY_BYTES: usize = 64 * 1024;
+
+#[derive(serde::Deserialize)]
+pub struct FetchRequest {
+    url: String,
+    content_type: String,
+}
+
+/// Whether an address is reachable only from inside the host or its network.
+fn is_internal_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_internal_address(ip.into()),
+            None => {
+                ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_unique_local()
+                    || ip.is_unicast_link_local()
+            }
+        },
+    }
+}
+
+/// Resolves the URL's host, refusing it when any address is internal.
+async fn resolve_public_host(url: &reqwest::Url) -> Result<Vec<SocketAddr>, Response> {
+    let forbidden = || {
+        (
+            StatusCode::BAD_REQUEST,
+            "URL must not point at an internal address",
+        )
+            .into_response()
+    };
+    let Some(host) = url.host_str() else {
+        return Err(forbidden());
+    };
+    let port = url.port_or_known_default().unwrap_or(443);
+    let addrs = match host.trim_start_matches('[').trim_end_matches(']').parse() {
+        Ok(ip) => vec![SocketAddr::new(ip, port)],
+        Err(_) => tokio::net::lookup_host((host, port))
+            .await
+            .map_err(|err| {
+                (StatusCode::BAD_GATEWAY, format!("Fetch failed: {err}")).into_response()
+            })?
+            .collect(),
+    };
+    if addrs.is_empty() || addrs.iter().any(|addr| is_internal_address(addr.ip())) {
+        return Err(forbidden());
+    }
+
+    Ok(addrs)
+}
+
+async fn fetch_body(url: reqwest::Url) -> Result<String, Response> {
+    let bad_gateway = |message: String| (StatusCode::BAD_GATEWAY, message).into_response();
+
+    // The client connects only to the addresses screened here, and redirects
+    // aren't followed: a hop could leave HTTPS or point at an internal host.
+    let addrs = resolve_public_host(&url).await?;
+    let mut client = reqwest::Client::builder()
+        .timeout(FETCH_TIMEOUT)
+        .https_only(true)
+        .redirect(reqwest::redirect::Policy::none());
+    if let Some(host) = url.host_str() {
+        client = client.resolve_to_addrs(host, &addrs);
+    }
+    let client = client.build().map_err(|err| bad_gateway(err.to_string()))?;
+    let mut response = client
+        .get(url)
+        .send()
+        .await
+        .map_err(|err| bad_gateway(format!("Fetch failed: {err}")))?;
+    if response.status().is_redirection() {
+        return Err(bad_gateway(format!(
+            "Upstream redirected ({}); redirects are not followed",
+            response.status()
+        )));
+    }
+    if !response.status().is_success() {
+        return Err(bad_gateway(format!(
+            "Upstream returned {}",
+            response.status()
+        )));
+    }
+
+    // Read chunk by chunk so an oversized body is dropped without buffering it all.
+    let mut body = Vec::new();
+    while let Some(chunk) = response
+        .chunk()
+        .await
+        .map_err(|err| bad_gateway(format!("Fetch failed: {err}")))?
+    {
+        if body.len() + chunk.len() > FETCH_MAX_BODY_BYTES {
+            return Err((
+                StatusCode::PAYLOAD_TOO_LARGE,
+                format!("Manifest exceeds {FETCH_MAX_BODY_BYTES} bytes"),
+            )
+                .into_response());
+        }
+        body.extend_from_slice(&chunk);
+    }
+
+    String::from_utf8(body).map_err(|_| ManifestParseError::InvalidManifest.into_response())
+}
+
+pub async fn fetch_manifest(
+    Query(params): Query<ManifestParameters>,
+    headers: HeaderMap,
+    Json(request): Json<FetchRequest>,
+) -> impl IntoResponse {
+    let url = match reqwest::Url::parse(&request.url) {
+        Ok(url) if url.scheme() == "https" => url,
+        _ => return (StatusCode::BAD_REQUEST, "URL must use https").into_response(),
+    };
+    if !is_manifest_content_type(&request.content_type) {
+        return ManifestParseError::InvalidContentType.into_response();
+    }
+
+    let body = match fetch_body(url).await {
+        Ok(body) => body,
+        Err(response) => return response,
+    };
+
+    parse_body(&request.content_type, body, &params)
+        .map(|mut result| {
+            result.format = ResponseFormat::from_accept(&headers);
+            result
+        })
+        .into_response()
+}
+
+const LINT_MAX_QUANTITY: u32 = 1000;
+const LINT_MAX_NAME_LEN: usize = 100;
+const LINT_MAX_DISTINCT_ITEMS: usize = 50;
+
+#[derive(serde::Serialize)]
+struct LintWarning {
+    item: Option<String>,
+    code: &'static str,
+    message: String,
+}
+
+#[derive(serde::Serialize)]
+struct LintReport {
+    warnings: Vec<LintWarning>,
+    valid_orders: usize,
+}
+
+fn lint_toys(toys: &[ValidToy]) -> Vec<LintWarning> {
+    let mut warnings = Vec::new();
+    for toy in toys {
+        let mut warn = |code, message| {
+            warnings.push(LintWarning {
+                item: Some(toy.item.clone()),
+                code,
+                message,
+            })
+        };
+
+        if toy.quantity > LINT_MAX_QUANTITY {
+            warn(
+                "QTY_TOO_HIGH",
+                format!("Quantity {} exceeds {LINT_MAX_QUANTITY}", toy.quantity),
+            );
+        }
+        if toy.item.chars().count() > LINT_MAX_NAME_LEN {
+            warn(
+                "NAME_TOO_LONG",
+                format!("Item name is longer than {LINT_MAX_NAME_LEN} characters"),
+            );
+        }
+        if !toy.item.is_empty() && toy.item.chars().all(|c| c.is_ascii_digit()) {
+            warn(
+                "NAME_NUMERIC",
+                "Item name contains only numbers".to_string(),
+            );
+        }
+    }
+
+    let distinct_items = toys.iter().map(|t| &t.item).collect::<HashSet<_>>().len();
+    if distinct_items > LINT_MAX_DISTINCT_ITEMS {
+        warnings.push(LintWarning {
+            item: None,
+            code: "TOO_MANY_ITEMS",
+            message: format!("{distinct_items} distinct items exceeds {LINT_MAX_DISTINCT_ITEMS}"),
+        });
+    }
+
+    warnings
+}
+
+/// Parses a manifest like `manifest` does, but reports style problems with
+/// the orders instead of storing them.
+pub async fn lint(
+    Query(params): Query<ManifestParameters>,
+    headers: HeaderMap,
+    body: String,
+) -> impl IntoResponse {
+    let Some(content_type) = content_type(&headers) else {
+        return (StatusCode::BAD_REQUEST, "Missing Content Type".to_string()).into_response();
+    };
+
+    match parse_body(content_type, body, &params) {
+        Ok(result) => Json(LintReport {
+            warnings: lint_toys(&result.toys),
+            valid_orders: result.toys.len(),
+        })
+        .into_response(),
+        Err(err) => err.into_response(),
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct EditionCheckParameters {
+    min_edition: Edition,
+}
+
+fn manifest_edition(manifest: &cargo_manifest::Manifest) -> Result<Edition, ManifestParseError> {
+    match manifest.package.as_ref().map(|p| &p.edition) {
+        Some(Some(MaybeInherited::Local(edition))) => Ok(*edition),
+        Some(None) => Ok(Edition::default()),
+        Some(Some(MaybeInherited::Inherited { .. })) => Err(ManifestParseError::InvalidManifest),
+        None => Err(ManifestParseError::WorkspaceManifest),
+    }
+}
+
+fn check_edition(
+    manifest: &cargo_manifest::Manifest,
+    min_edition: Edition,
+) -> Result<Edition, ManifestParseError> {
+    let edition = manifest_edition(manifest)?;
+
+    // Editions are years, so their names order the same way the editions do.
+    if edition.as_str() < min_edition.as_str() {
+        return Err(ManifestParseError::EditionTooOld {
+            found: edition.as_str().to_string(),
+            minimum: min_edition.as_str().to_string(),
+        });
+    }
+
+    Ok(edition)
+}
+
+pub async fn edition_check(
+    Query(params): Query<EditionCheckParameters>,
+    headers: HeaderMap,
+    body: String,
+) -> impl IntoResponse {
+    let Some(content_type) = content_type(&headers) else {
+        return (StatusCode::BAD_REQUEST, "Missing Content Type".to_string()).into_response();
+    };
+
+    cargo_manifest_from(content_type, &body)
+        .and_then(|manifest| check_edition(&manifest, params.min_edition))
+        .map(|edition| edition.as_str())
+        .into_response()
+}
+
+const EDITION_GUIDE_URL: &str = "https://doc.rust-lang.org/edition-guide/";
+
+#[derive(serde::Serialize)]
+pub struct EditionSuggestion {
+    edition: &'static str,
+    suggested_edition: Option<&'static str>,
+    changes: &'static [&'static str],
+    migration_guide_url: &'static str,
+}
+
+/// Static upgrade advice for the manifest's edition; nothing in the crate
+/// itself is inspected.
+fn suggest_edition(edition: Edition) -> EditionSuggestion {
+    let (suggested_edition, changes): (_, &[_]) = match edition {
+        Edition::E2015 => (
+            Some("2021"),
+            &[
+                "Module system: paths in `use` start from the crate root or an extern crate",
+                "Anonymous lifetime `'_` for elided lifetimes in paths",
+                "Or-patterns allowed inside nested patterns",
+            ],
+        ),
+        Edition::E2018 => (
+            Some("2021"),
+            &[
+                "Disjoint closure captures",
+                "IntoIterator for arrays",
+                "Or-patterns in macro_rules `$x:pat`",
+                "Default Cargo feature resolver 2",
+                "TryFrom, TryInto and FromIterator in the prelude",
+                "Reserved prefixes for identifiers and literals",
+                "Panic macros require format strings",
+            ],
+        ),
+        Edition::E2021 | Edition::E2024 => (None, &[]),
+    };
+
+    EditionSuggestion {
+        edition: edition.as_str(),
+        suggested_edition,
+        changes,
+        migration_guide_url: EDITION_GUIDE_URL,
+    }
+}
+
+pub async fn suggest_manifest_edition(body: String) -> impl IntoResponse {
+    cargo_manifest_from("application/toml", &body)
+        .and_then(|manifest| manifest_edition(&manifest))
+        .map(|edition| Json(suggest_edition(edition)))
+        .into_response()
+}
+
+#[derive(serde::Deserialize)]
+pub struct CompareVersionsParameters {
+    #[serde(default)]
+    from_manifests: bool,
+}
+
+#[derive(serde::Deserialize)]
+struct VersionPair {
+    a: String,
+    b: String,
+}
+
+#[derive(serde::Deserialize)]
+struct ManifestPair {
+    base: String,
+    head: String,
+}
+
+#[derive(serde::Serialize)]
+pub struct VersionComparison {
+    result: &'static str,
+    diff_type: &'static str,
+}
+
+fn parse_version(version: &str) -> Result<semver::Version, ManifestParseError> {
+    semver::Version::parse(version)
+        .map_err(|_| ManifestParseError::InvalidVersion(version.to_string()))
+}
+
+fn manifest_version(body: &str) -> Result<String, ManifestParseError> {
+    let manifest = cargo_manifest_from("application/toml", body)?;
+    match manifest.package.as_ref().map(|p| p.version()) {
+        Some(MaybeInherited::Local(version)) => Ok(version.to_string()),
+        Some(MaybeInherited::Inherited { .. }) => Err(ManifestParseError::InvalidManifest),
+        None => Err(ManifestParseError::WorkspaceManifest),
+    }
+}
+
+fn compare_versions(a: &str, b: &str) -> Result<VersionComparison, ManifestParseError> {
+    let (a, b) = (parse_version(a)?, parse_version(b)?);
+
+    let result = match a.cmp_precedence(&b) {
+        Ordering::Less => "less",
+        Ordering::Equal => "equal",
+        Ordering::Greater => "greater",
+    };
+    let diff_type = if a.major != b.major {
+        "major"
+    } else if a.minor != b.minor {
+        "minor"
+    } else if a.patch != b.patch {
+        "patch"
+    } else if a.pre != b.pre {
+        "pre"
+    } else {
+        "none"
+    };
+
+    Ok(VersionComparison { result, diff_type })
+}
+
+pub async fn compare_manifest_versions(
+    Query(params): Query<CompareVersionsParameters>,
+    body: String,
+) -> impl IntoResponse {
+    let versions = if params.from_manifests {
+        serde_json::from_str::<ManifestPair>(&body)
+            .map_err(|_| ManifestParseError::InvalidManifest)
+            .and_then(|pair| Ok((manifest_version(&pair.base)?, manifest_version(&pair.head)?)))
+    } else {
+        serde_json::from_str::<VersionPair>(&body)
+            .map(|pair| (pair.a, pair.b))
+            .map_err(|_| ManifestParseError::InvalidManifest)
+    };
+
+    versions
+        .and_then(|(a, b)| compare_versions(&a, &b))
+        .map(Json)
+        .into_response()
+}
+
+#[derive(serde::Deserialize)]
+pub struct ChangelogCheckRequest {
+    old_version: String,
+    new_version: String,
+    has_breaking_changes: bool,
+}
+
+#[derive(serde::Serialize)]
+pub struct ChangelogCheck {
+    consistent: bool,
+    bump_type: &'static str,
+    message: String,
+}
+
+fn check_changelog(request: &ChangelogCheckRequest) -> Result<ChangelogCheck, ManifestParseError> {
+    let old = parse_version(&request.old_version)?;
+    let new = parse_version(&request.new_version)?;
+
+    if new.cmp_precedence(&old) != Ordering::Greater {
+        return Ok(ChangelogCheck {
+            consistent: false,
+            bump_type: "none",
+            message: format!("{new} is not newer than {old}"),
+        });
+    }
+
+    let bump_type = if new.major != old.major {
+        "major"
+    } else if new.minor != old.minor {
+        "minor"
+    } else {
+        "patch"
+    };
+    let consistent = !request.has_breaking_changes || bump_type == "major";
+    let message = match (request.has_breaking_changes, bump_type) {
+        (true, "major") => "Breaking changes are released in a major bump".to_string(),
+        (true, _) => format!("Breaking changes require a major bump, not {bump_type}"),
+        (false, "patch") => "Patch bump for fixes only".to_string(),
+        (false, _) => format!("A {bump_type} bump without breaking changes is fine"),
+    };
+
+    Ok(ChangelogCheck {
+        consistent,
+        bump_type,
+        message,
+    })
+}
+
+pub async fn changelog_check(Json(request): Json<ChangelogCheckRequest>) -> impl IntoResponse {
+    check_changelog(&request).map(Json).into_response()
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    Sha256::digest(data)
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+const MAX_STARRED_MANIFESTS: usize = 100;
+
+/// Fingerprints of starred manifests. Once full, starring another evicts the
+/// one starred longest ago.
+#[derive(Default)]
+pub struct StarredManifests {
+    fingerprints: HashSet<String>,
+    order: VecDeque<String>,
+}
+
+impl StarredManifests {
+    fn insert(&mut self, fingerprint: String) -> bool {
+        if !self.fingerprints.insert(fingerprint.clone()) {
+            return false;
+        }
+        self.order.push_back(fingerprint);
+        if self.order.len() > MAX_STARRED_MANIFESTS {
+            if let Some(oldest) = self.order.pop_front() {
+                self.fingerprints.remove(&oldest);
+            }
+        }
+        true
+    }
+
+    fn remove(&mut self, fingerprint: &str) -> bool {
+        if !self.fingerprints.remove(fingerprint) {
+            return false;
+        }
+        self.order.retain(|f| f != fingerprint);
+        true
+    }
+
+    fn contains(&self, fingerprint: &str) -> bool {
+        self.fingerprints.contains(fingerprint)
+    }
+}
+
+/// SHA-256 of the sorted plain-text toy listing, so the same orders give the
+/// same fingerprint whatever format or order they were written in.
+fn manifest_fingerprint(content_type: &str, body: String) -> Result<String, ManifestParseError> {
+    let result = parse_body(content_type, body, &ManifestParameters::default())?;
+
+    let mut lines = result
+        .toys
+        .iter()
+        .map(|t| t.to_string())
+        .collect::<Vec<_>>();
+    lines.sort();
+
+    Ok(sha256_hex(lines.join("\n").as_bytes()))
+}
+
+pub async fn star_manifest(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: String,
+) -> impl IntoResponse {
+    let Some(content_type) = content_type(&headers) else {
+        return (StatusCode::BAD_REQUEST, "Missing Content Type").into_response();
+    };
+    let fingerprint = match manifest_fingerprint(content_type, body) {
+        Ok(fingerprint) => fingerprint,
+        Err(err) => return err.into_response(),
+    };
+
+    let status = if state.stars.write().unwrap().insert(fingerprint.clone()) {
+        StatusCode::CREATED
+    } else {
+        StatusCode::OK
+    };
+    (status, fingerprint).into_response()
+}
+
+pub async fn unstar_manifest(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: String,
+) -> impl IntoResponse {
+    let Some(content_type) = content_type(&headers) else {
+        return (StatusCode::BAD_REQUEST, "Missing Content Type").into_response();
+    };
+    let fingerprint = match manifest_fingerprint(content_type, body) {
+        Ok(fingerprint) => fingerprint,
+        Err(err) => return err.into_response(),
+    };
+
+    if state.stars.write().unwrap().remove(&fingerprint) {
+        StatusCode::NO_CONTENT.into_response()
+    } else {
+        StatusCode::NOT_FOUND.into_response()
+    }
+}
+
+pub async fn list_stars(State(state): State<AppState>) -> impl IntoResponse {
+    Json(state.stars.read().unwrap().order.clone())
+}
+
+#[derive(serde::Serialize)]
+pub struct StarredStatus {
+    starred: bool,
+}
+
+pub async fn starred(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: String,
+) -> impl IntoResponse {
+    let Some(content_type) = content_type(&headers) else {
+        return (StatusCode::BAD_REQUEST, "Missing Content Type").into_response();
+    };
+
+    match manifest_fingerprint(content_type, body) {
+        Ok(fingerprint) => Json(StarredStatus {
+            starred: state.stars.read().unwrap().contains(&fingerprint),
+        })
+        .into_response(),
+        Err(err) => err.into_response(),
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct ReproHash {
+    hash: String,
+    normalized: String,
+}
+
+/// Tables keep their insertion order once another crate enables toml's
+/// `preserve_order`, so keys are sorted explicitly.
+fn sort_keys(value: Value) -> Value {
+    match value {
+        Value::Table(table) => Value::Table(
+            table
+                .into_iter()
+                .map(|(key, value)| (key, sort_keys(value)))
+                .collect::<BTreeMap<_, _>>()
+                .into_iter()
+                .collect(),
+        ),
+        Value::Array(values) => Value::Array(values.into_iter().map(sort_keys).collect()),
+        value => value,
+    }
+}
+
+/// Re-serializing with sorted keys drops formatting and key order, so
+/// equivalent manifests hash the same.
+fn repro_hash(body: &str) -> Result<ReproHash, ManifestParseError> {
+    let value = toml::from_str::<Value>(body).map_err(|_| ManifestParseError::InvalidManifest)?;
+    let value = sort_keys(value);
+    let normalized =
+        toml::to_string_pretty(&value).map_err(|_| ManifestParseError::InvalidManifest)?;
+
+    Ok(ReproHash {
+        hash: sha256_hex(normalized.as_bytes()),
+        normalized,
+    })
+}
+
+pub async fn manifest_repro_hash(body: String) -> impl IntoResponse {
+    repro_hash(&body).map(Json).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn package(toml: &str) -> cargo_manifest::Package {
+        cargo_manifest::Manifest::from_str(toml)
+            .unwrap()
+            .package
+            .unwrap()
+    }
+
+    #[test]
+    fn reports_include_and_exclude_patterns() {
+        let package = package(
+            r#"
+            [package]
+            name = "patterns"
+            include = ["src/**/*.rs", "assets/*", "Cargo.toml"]
+            exclude = ["assets/*", "tests/"]
+            "#,
+        );
+        let mut headers = HeaderMap::new();
+        insert_pattern_headers(&package, &mut headers);
+
+        assert_eq!(
+            headers["X-Include-Patterns"],
+            "src/**/*.rs,assets/*,Cargo.toml"
+        );
+        assert_eq!(headers["X-Exclude-Patterns"], "assets/*,tests/");
+        assert_eq!(
+            headers.get_all("X-Warning").iter().collect::<Vec<_>>(),
+            ["conflicting-pattern:assets/*"]
+        );
+    }
+
+    #[test]
+    fn omits_pattern_headers_without_patterns() {
+        let package = package(
+            r#"
+            [package]
+            name = "no-patterns"
+            "#,
+        );
+        let mut headers = HeaderMap::new();
+        insert_pattern_headers(&package, &mut headers);
+
+        assert!(headers.is_empty());
+    }
+
+    const ORDERS_HEADER: &str = r#"
+        [package]
+        name = "orders"
+        keywords = ["Christmas 2024"]
+    "#;
+
+    #[test]
+    fn skips_orders_below_minimum_quantity() {
+        let body = format!(
+            r#"{ORDERS_HEADER}
+            [[package.metadata.orders]]
+            item = "Train"
+            quantity = 10
+            min_order_qty = 5
+
+            [[package.metadata.orders]]
+            item = "Rocket"
+            quantity = 2
+            min_order_qty = 5
+
+            [[package.metadata.orders]]
+            item = "Doll"
+            quantity = 1
+
+            [[package.metadata.orders]]
+            item = "Kite"
+            quantity = 3
+            min_order_qty = 4
+            "#
+        );
+        let result = parse_toml(body, &ManifestParameters::default()).unwrap();
+
+        let items = result
+            .toys
+            .iter()
+            .map(|t| t.item.as_str())
+            .collect::<Vec<_>>();
+        assert_eq!(items, ["Train", "Doll"]);
+        assert_eq!(result.headers["X-Below-Minimum-Qty"], "Rocket,Kite");
+    }
+
+    #[test]
+    fn omits_below_minimum_header_when_all_orders_qualify() {
+        let body = format!(
+            r#"{ORDERS_HEADER}
+            [[package.metadata.orders]]
+            item = "Train"
+            quantity = 5
+            min_order_qty = 5
+            "#
+        );
+        let result = parse_toml(body, &ManifestParameters::default()).unwrap();
+
+        assert_eq!(result.toys.len(), 1);
+        assert!(!result.headers.contains_key("X-Below-Minimum-Qty"));
+    }
+
+    #[test]
+    fn rejects_workspace_manifests() {
+        let body = r#"
+            [workspace]
+            members = ["crates/*"]
+        "#;
+
+        assert!(matches!(
+            parse_toml(body.to_string(), &ManifestParameters::default()),
+            Err(ManifestParseError::WorkspaceManifest)
+        ));
+    }
+
+    #[test]
+    fn rejects_duplicate_binary_target_names() {
+        let body = r#"
+            [package]
+            name = "toys"
+            keywords = ["Christmas 2024"]
+
+            [[bin]]
+            name = "sleigh"
+            path = "src/main.rs"
+
+            [[bin]]
+            name = "sleigh"
+            path = "src/bin/other.rs"
+
+            [[package.metadata.orders]]
+            item = "Toy car"
+            quantity = 2
+        "#;
+
+        let err = parse_toml(body.to_string(), &ManifestParameters::default())
+            .err()
+            .unwrap();
+        assert!(
+            matches!(err, ManifestParseError::DuplicateBinaryTarget(ref name) if name == "sleigh")
+        );
+        assert_eq!(err.to_string(), "Duplicate binary target name: sleigh");
+    }
+
+    fn duplicate_orders() -> String {
+        format!(
+            r#"{ORDERS_HEADER}
+            [[package.metadata.orders]]
+            item = "Train"
+            quantity = 2
+            category = "Vehicles"
+
+            [[package.metadata.orders]]
+            item = "Rocket"
+            quantity = 1
+
+            [[package.metadata.orders]]
+            item = "Train"
+            quantity = 3
+
+            [[package.metadata.orders]]
+            item = "Rocket"
+            quantity = 4
+
+            [[package.metadata.orders]]
+            item = "Doll"
+            quantity = 1
+            "#
+        )
+    }
+
+    #[test]
+    fn reports_duplicate_orders() {
+        let result = parse_toml(duplicate_orders(), &ManifestParameters::default()).unwrap();
+
+        assert_eq!(result.toys.len(), 5);
+        assert_eq!(result.headers["X-Duplicate-Orders"], "Train,Rocket");
+    }
+
+    #[test]
+    fn merges_duplicate_orders_when_deduplicating() {
+        let params = ManifestParameters {
+            dedup: true,
+            ..Default::default()
+        };
+        let result = parse_toml(duplicate_orders(), &params).unwrap();
+
+        let orders = result
+            .toys
+            .iter()
+            .map(|t| (t.item.as_str(), t.quantity))
+            .collect::<Vec<_>>();
+        assert_eq!(orders, [("Train", 5), ("Rocket", 5), ("Doll", 1)]);
+        assert_eq!(result.toys[0].category.as_deref(), Some("Vehicles"));
+        assert_eq!(result.headers["X-Duplicate-Orders"], "Train,Rocket");
+    }
+
+    #[test]
+    fn saturates_merged_quantities() {
+        let params = ManifestParameters {
+            dedup: true,
+            ..Default::default()
+        };
+        let orders = "[[package.metadata.orders]]\nitem = \"Train\"\nquantity = 3000000000\n";
+        let result = parse_toml(format!("{ORDERS_HEADER}\n{orders}{orders}"), &params).unwrap();
+
+        assert_eq!(result.toys.len(), 1);
+        assert_eq!(result.toys[0].quantity, u32::MAX);
+    }
+
+    #[test]
+    fn rejects_first_duplicate_order_when_strict() {
+        let params = ManifestParameters {
+            strict: true,
+            ..Default::default()
+        };
+        let err = parse_toml(duplicate_orders(), &params).err().unwrap();
+
+        assert!(matches!(err, ManifestParseError::DuplicateOrder(ref item) if item == "Train"));
+        assert_eq!(err.to_string(), "Duplicate order: Train");
+    }
+
+    #[test]
+    fn writes_escaped_xml_orders() {
+        let body = format!(
+            r#"{ORDERS_HEADER}
+            [[package.metadata.orders]]
+            item = "Trains & <Cars>"
+            quantity = 5
+
+            [[package.metadata.orders]]
+            item = "Doll"
+            quantity = 1
+            "#
+        );
+        let result = parse_toml(body, &ManifestParameters::default()).unwrap();
+        let xml = orders_xml(&result.toys).unwrap();
+
+        let mut reader = quick_xml::Reader::from_reader(xml.as_slice());
+        let (mut depth, mut texts) = (0, Vec::new());
+        loop {
+            match reader.read_event().unwrap() {
+                Event::Start(_) => depth += 1,
+                Event::End(_) => depth -= 1,
+                Event::Text(text) => texts.push(text.unescape().unwrap().into_owned()),
+                Event::Eof => break,
+                _ => {}
+            }
+        }
+        assert_eq!(depth, 0);
+        assert_eq!(texts, ["Trains & <Cars>", "5", "Doll", "1"]);
+    }
+
+    #[test]
+    fn evicts_the_oldest_star_when_full() {
+        let mut stars = StarredManifests::default();
+        for i in 0..=MAX_STARRED_MANIFESTS {
+            assert!(stars.insert(i.to_string()));
+        }
+
+        assert!(!stars.contains("0"));
+        assert!(stars.contains("1"));
+        assert!(stars.contains(&MAX_STARRED_MANIFESTS.to_string()));
+        assert_eq!(stars.order.len(), MAX_STARRED_MANIFESTS);
+        assert!(!stars.insert("1".to_string()));
+    }
+
+    #[tokio::test]
+    async fn streams_one_chunk_per_toy_line() {
+        let orders = (0..500)
+            .map(|i| format!("[[package.metadata.orders]]\nitem = \"Toy {i}\"\nquantity = {i}\n"))
+            .collect::<String>();
+        let result = parse_toml(
+            format!("{ORDERS_HEADER}\n{orders}"),
+            &ManifestParameters::default(),
+        )
+        .unwrap();
+
+        let response = result.into_response();
+        assert_eq!(
+            response.headers()[header::CONTENT_TYPE],
+            "text/plain; charset=utf-8"
+        );
+
+        let mut stream = response.into_body().into_data_stream();
+        let mut chunks = Vec::new();
+        while let Some(chunk) = tokio_stream::StreamExt::next(&mut stream).await {
+            chunks.push(String::from_utf8(chunk.unwrap().to_vec()).unwrap());
+        }
+        assert_eq!(chunks.len(), 500);
+        assert_eq!(chunks[0], "Toy 0: 0");
+        assert_eq!(chunks[499], "\nToy 499: 499");
+        let expected = (0..500)
+            .map(|i| format!("Toy {i}: {i}"))
+            .collect::<Vec<_>>()
+            .join("\n");
+        assert_eq!(chunks.concat(), expected);
+    }
+
+    #[test]
+    fn equivalent_manifests_share_a_repro_hash() {
+        let a = repro_hash("[package]\nname = \"a\"\nversion = \"0.1.0\"\n").unwrap();
+        let b = repro_hash("[package]\nversion = \"0.1.0\"   # bumped\n\nname = 'a'\n").unwrap();
+
+        assert_eq!(a.hash, b.hash);
+        assert_eq!(a.normalized, b.normalized);
+        assert!(repro_hash("[package").is_err());
+    }
+
+    #[test]
+    fn restocking_replaces_existing_items() {
+        let toys = |orders: &str| {
+            parse_toml(
+                format!("{ORDERS_HEADER}\n{orders}"),
+                &ManifestParameters::default(),
+            )
+            .unwrap()
+            .toys
+        };
+        let mut inventory = Vec::new();
+        restock(
+            &mut inventory,
+            toys("[[package.metadata.orders]]\nitem = \"Train\"\nquantity = 1\n"),
+        );
+        restock(
+            &mut inventory,
+            toys("[[package.metadata.orders]]\nitem = \"Train\"\nquantity = 7\n"),
+        );
+
+        assert_eq!(inventory.len(), 1);
+        assert_eq!(inventory[0].quantity, 7);
+    }
+
+    #[test]
+    fn flags_negative_quantities_before_the_cast() {
+        let body = format!(
+            r#"{ORDERS_HEADER}
+            [[package.metadata.orders]]
+            item = "Huge"
+            quantity = 3000000000
+
+            [[package.metadata.orders]]
+            item = "Wraps to zero"
+            quantity = -4294967296
+
+            [[package.metadata.orders]]
+            item = "Wraps to one"
+            quantity = -4294967295
+            "#
+        );
+        let result = parse_toml(body, &ManifestParameters::default()).unwrap();
+
+        let negative = result
+            .toys
+            .iter()
+            .filter_map(|t| NoNegativeQuantity.check(t))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            negative,
+            [
+                "Wraps to zero has a negative quantity",
+                "Wraps to one has a negative quantity"
+            ]
+        );
+    }
+
+    #[test]
+    fn rejects_valid_cargo_manifests_with_unexpected_shapes() {
+        for body in [
+            "[package]\nname = \"a\"\nkeywords = { workspace = true }\n",
+            "[package]\nname = \"a\"\nkeywords = [\"Christmas 2024\"]\n[package.metadata]\norders = \"x\"\n",
+        ] {
+            assert!(matches!(
+                parse_toml(body.to_string(), &ManifestParameters::default()),
+                Err(ManifestParseError::InvalidManifest)
+            ));
+        }
+        assert!(matches!(
+            parse_json(
+                r#"{"package":{"name":"a","keywords":{"workspace":true}}}"#.to_string(),
+                &ManifestParameters::default()
+            ),
+            Err(ManifestParseError::InvalidManifest)
+        ));
+    }
+
+    #[tokio::test]
+    async fn reports_parse_time_on_early_returns() {
+        let response = manifest(
+            State(AppState::default()),
+            Query(ManifestParameters::default()),
+            HeaderMap::new(),
+            String::new(),
+        )
+        .await
+        .into_response();
+
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert!(response.headers().contains_key("X-Parse-Time-Ms"));
+    }
+
+    #[test]
+    fn leaves_unset_order_bounds_open() {
+        let bounds = |min_orders, max_orders| ManifestParameters {
+            min_orders,
+            max_orders,
+            ..Default::default()
+        };
+
+        assert_eq!(
+            check_order_count(1, &bounds(Some(2), None)),
+            Err("Order count 1 outside expected range [2, ∞)".to_string())
+        );
+        assert_eq!(
+            check_order_count(5, &bounds(None, Some(3))),
+            Err("Order count 5 outside expected range [0, 3]".to_string())
+        );
+        assert_eq!(
+            check_order_count(5, &bounds(Some(1), Some(3))),
+            Err("Order count 5 outside expected range [1, 3]".to_string())
+        );
+        assert_eq!(check_order_count(2, &bounds(Some(1), Some(3))), Ok(()));
+    }
+
+    #[tokio::test]
+    async fn refuses_to_fetch_internal_addresses() {
+        for url in [
+            "https://127.0.0.1/Cargo.toml",
+            "https://169.254.169.254/latest",
+            "https://10.0.0.1/",
+            "https://192.168.1.1/",
+            "https://0.0.0.0/",
+            "https://[::1]/",
+            "https://[fd00::1]/",
+            "https://[::ffff:127.0.0.1]/",
+        ] {
+            let url = reqwest::Url::parse(url).unwrap();
+            let response = resolve_public_host(&url).await.unwrap_err();
+            assert_eq!(response.status(), StatusCode::BAD_REQUEST, "{url}");
+        }
+
+        let url = reqwest::Url::parse("https://1.1.1.1/").unwrap();
+        assert!(resolve_public_host(&url).await.is_ok());
+    }
+
+    #[test]
+    fn totals_large_tax_quantities_without_overflow() {
+        let orders = "[[package.metadata.orders]]\nitem = \"Train\"\nquantity = 3000000000\n";
+        let result = parse_toml(
+            format!("{ORDERS_HEADER}\n{orders}{orders}"),
+            &ManifestParameters::default(),
+        )
+        .unwrap();
+
+        let total = tax_breakdown(&result.toys)
+            .values()
+            .map(|summary| summary.total_quantity)
+            .sum::<u64>();
+        assert_eq!(total, 6_000_000_000);
+    }
+
+    #[test]
+    fn flags_numeric_names_but_not_empty_ones() {
+        let orders = "[[package.metadata.orders]]\nitem = \"\"\nquantity = 1\n\
+                      [[package.metadata.orders]]\nitem = \"123\"\nquantity = 1\n";
+        let result = parse_toml(
+            format!("{ORDERS_HEADER}\n{orders}"),
+            &ManifestParameters::default(),
+        )
+        .unwrap();
+
+        let numeric = lint_toys(&result.toys)
+            .into_iter()
+            .filter(|w| w.code == "NAME_NUMERIC")
+            .filter_map(|w| w.item)
+            .collect::<Vec<_>>();
+        assert_eq!(numeric, ["123"]);
+    }
+
+    #[test]
+    fn parses_only_hex_digit_colors() {
+        let rgb = Rgb::parse("#0a0B0c").unwrap();
+        assert_eq!((rgb.r, rgb.g, rgb.b), (10, 11, 12));
+        assert!(Rgb::parse("#+f+f+f").is_none());
+        assert!(Rgb::parse("#0a0b0").is_none());
     }
-    .into_response()
 }
@@ -0,0 +1,680 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use axum::{
+    Json,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    response::IntoResponse,
+};
+use chrono::{Datelike, NaiveDate};
+
+use crate::{
+    AppState,
+    dangerous_open_internet::{
+        SALES_CHANNELS, SHIPPING_CLASSES, SUBSCRIPTION_FREQUENCIES, ValidToy,
+        is_valid_availability_zone, tax_breakdown,
+    },
+};
+
+pub async fn high_risk(State(state): State<AppState>) -> impl IntoResponse {
+    let toys = state
+        .inventory
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|t| t.is_high_risk())
+        .cloned()
+        .collect::<Vec<_>>();
+
+    Json(toys)
+}
+
+pub async fn shipping(State(state): State<AppState>) -> impl IntoResponse {
+    let mut counts = SHIPPING_CLASSES
+        .iter()
+        .map(|class| (class.to_string(), 0))
+        .collect::<BTreeMap<_, usize>>();
+    for toy in state.inventory.read().unwrap().iter() {
+        *counts.entry(toy.shipping_class().to_string()).or_default() += 1;
+    }
+
+    Json(counts)
+}
+
+#[derive(serde::Deserialize)]
+pub struct DiscountedParameters {
+    #[serde(default)]
+    min: f64,
+}
+
+pub async fn discounted(
+    State(state): State<AppState>,
+    params: Query<DiscountedParameters>,
+) -> impl IntoResponse {
+    let mut toys = state
+        .inventory
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|t| t.discount_pct.is_some_and(|pct| pct >= params.min))
+        .cloned()
+        .collect::<Vec<_>>();
+    toys.sort_by(|a, b| b.discount_pct.unwrap().total_cmp(&a.discount_pct.unwrap()));
+
+    Json(toys)
+}
+
+#[derive(serde::Deserialize)]
+pub struct FragileParameters {
+    min_level: u8,
+}
+
+pub async fn fragile(
+    State(state): State<AppState>,
+    params: Query<FragileParameters>,
+) -> impl IntoResponse {
+    let toys = state
+        .inventory
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|t| t.fragility.is_some_and(|level| level >= params.min_level))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    Json(toys)
+}
+
+#[derive(serde::Deserialize)]
+pub struct TopRatedParameters {
+    #[serde(default)]
+    min_score: f64,
+}
+
+pub async fn top_rated(
+    State(state): State<AppState>,
+    params: Query<TopRatedParameters>,
+) -> impl IntoResponse {
+    let mut toys = state
+        .inventory
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|t| {
+            t.review_score
+                .is_some_and(|score| score >= params.min_score)
+        })
+        .cloned()
+        .collect::<Vec<_>>();
+    toys.sort_by(ValidToy::cmp_by_review_score);
+
+    Json(toys)
+}
+
+#[derive(serde::Deserialize)]
+pub struct ReadyParameters {
+    by: NaiveDate,
+}
+
+pub async fn ready(
+    State(state): State<AppState>,
+    params: Query<ReadyParameters>,
+) -> impl IntoResponse {
+    let today = chrono::Local::now().naive_local().date();
+    if params.by < today {
+        return (StatusCode::BAD_REQUEST, "Target date is in the past").into_response();
+    }
+
+    let toys = state
+        .inventory
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|t| t.arrives_by(today, params.by))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    Json(toys).into_response()
+}
+
+#[derive(serde::Deserialize)]
+pub struct PopularParameters {
+    top: usize,
+}
+
+#[derive(serde::Serialize)]
+pub struct PopularToy {
+    item: String,
+    rank: Option<u32>,
+    quantity: u32,
+}
+
+pub async fn popular(
+    State(state): State<AppState>,
+    params: Query<PopularParameters>,
+) -> impl IntoResponse {
+    let inventory = state.inventory.read().unwrap();
+    if params.top == 0 || params.top > inventory.len() {
+        return (
+            StatusCode::BAD_REQUEST,
+            format!("top must be between 1 and {}", inventory.len()),
+        )
+            .into_response();
+    }
+
+    let mut toys = inventory.iter().collect::<Vec<_>>();
+    // Unranked toys sort after every ranked one, then by name.
+    toys.sort_by_key(|t| (t.popularity_rank.is_none(), t.popularity_rank, &t.item));
+
+    let popular = toys
+        .into_iter()
+        .take(params.top)
+        .map(|t| PopularToy {
+            item: t.item.clone(),
+            rank: t.popularity_rank,
+            quantity: t.quantity,
+        })
+        .collect::<Vec<_>>();
+
+    Json(popular).into_response()
+}
+
+#[derive(serde::Serialize)]
+pub struct ToySet {
+    set_id: String,
+    set_name: String,
+    items: Vec<ValidToy>,
+    total_quantity: u64,
+}
+
+pub async fn sets(State(state): State<AppState>) -> impl IntoResponse {
+    let mut sets = BTreeMap::<&str, ToySet>::new();
+    let inventory = state.inventory.read().unwrap();
+    for toy in inventory.iter() {
+        let Some((set_id, set_name)) = toy.set() else {
+            continue;
+        };
+        let set = sets.entry(set_id).or_insert_with(|| ToySet {
+            set_id: set_id.to_string(),
+            set_name: set_name.to_string(),
+            items: Vec::new(),
+            total_quantity: 0,
+        });
+        set.items.push(toy.clone());
+        set.total_quantity += u64::from(toy.quantity);
+    }
+
+    Json(sets.into_values().collect::<Vec<_>>())
+}
+
+pub async fn components(State(state): State<AppState>) -> impl IntoResponse {
+    let leaves = state
+        .inventory
+        .read()
+        .unwrap()
+        .iter()
+        .flat_map(|t| t.leaves())
+        .cloned()
+        .collect::<Vec<_>>();
+
+    Json(leaves)
+}
+
+pub async fn units(State(state): State<AppState>) -> impl IntoResponse {
+    let units = state
+        .inventory
+        .read()
+        .unwrap()
+        .iter()
+        .filter_map(|t| t.quantity_unit.clone())
+        .collect::<BTreeSet<_>>();
+
+    Json(units)
+}
+
+pub async fn tax_summary(State(state): State<AppState>) -> impl IntoResponse {
+    Json(tax_breakdown(state.inventory.read().unwrap().iter()))
+}
+
+#[derive(Default, serde::Serialize)]
+pub struct RecyclingSummary {
+    by_code: BTreeMap<u8, usize>,
+    recyclable_items: usize,
+    non_recyclable_items: usize,
+}
+
+pub async fn recycling(State(state): State<AppState>) -> impl IntoResponse {
+    let mut summary = RecyclingSummary::default();
+    for toy in state.inventory.read().unwrap().iter() {
+        let Some(code) = toy.recycling_code else {
+            continue;
+        };
+        *summary.by_code.entry(code).or_default() += 1;
+        if toy.recyclable == Some(true) {
+            summary.recyclable_items += 1;
+        } else {
+            summary.non_recyclable_items += 1;
+        }
+    }
+
+    Json(summary)
+}
+
+#[derive(serde::Serialize)]
+pub struct CarbonTotal {
+    total_kg_co2e: f64,
+    coverage_pct: f64,
+}
+
+pub async fn carbon_total(State(state): State<AppState>) -> impl IntoResponse {
+    let inventory = state.inventory.read().unwrap();
+    let footprints = inventory
+        .iter()
+        .filter_map(|t| Some(t.carbon_kg_co2e? * f64::from(t.quantity)))
+        .collect::<Vec<_>>();
+    let coverage_pct = match inventory.len() {
+        0 => 0.0,
+        len => footprints.len() as f64 / len as f64 * 100.0,
+    };
+
+    Json(CarbonTotal {
+        total_kg_co2e: footprints.iter().sum(),
+        coverage_pct,
+    })
+}
+
+#[derive(serde::Deserialize)]
+pub struct OptionsParameters {
+    item: String,
+}
+
+#[derive(serde::Serialize)]
+pub struct ToyOptions {
+    options: HashMap<String, Vec<String>>,
+}
+
+pub async fn options(
+    State(state): State<AppState>,
+    params: Query<OptionsParameters>,
+) -> impl IntoResponse {
+    let inventory = state.inventory.read().unwrap();
+    let Some(toy) = inventory.iter().find(|t| t.item == params.item) else {
+        return (StatusCode::NOT_FOUND, "Item not found").into_response();
+    };
+
+    Json(ToyOptions {
+        options: toy.options.clone().unwrap_or_default(),
+    })
+    .into_response()
+}
+
+pub async fn channel(
+    State(state): State<AppState>,
+    Path(channel): Path<String>,
+) -> impl IntoResponse {
+    if !SALES_CHANNELS.contains(&channel.as_str()) {
+        return (StatusCode::NOT_FOUND, "Unknown sales channel").into_response();
+    }
+
+    let toys = state
+        .inventory
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|t| t.sold_through(&channel))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    Json(toys).into_response()
+}
+
+/// Subscriptions bill on the first day of the next month, quarter or year.
+fn next_billing_date(frequency: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let months = match frequency {
+        "monthly" => 1,
+        "quarterly" => 3 - today.month0() % 3,
+        "annual" => 13 - today.month(),
+        _ => return None,
+    };
+
+    today
+        .with_day(1)?
+        .checked_add_months(chrono::Months::new(months))
+}
+
+#[derive(serde::Deserialize)]
+pub struct SubscriptionsParameters {
+    frequency: Option<String>,
+}
+
+#[derive(serde::Serialize)]
+pub struct SubscriptionGroup {
+    next_billing_date: NaiveDate,
+    items: Vec<ValidToy>,
+}
+
+pub async fn subscriptions(
+    State(state): State<AppState>,
+    params: Query<SubscriptionsParameters>,
+) -> impl IntoResponse {
+    let frequencies = match params.frequency.as_deref() {
+        None => &SUBSCRIPTION_FREQUENCIES[..],
+        Some(frequency) => match SUBSCRIPTION_FREQUENCIES
+            .iter()
+            .position(|f| *f == frequency)
+        {
+            Some(i) => &SUBSCRIPTION_FREQUENCIES[i..=i],
+            None => {
+                return (StatusCode::BAD_REQUEST, "Unknown subscription frequency").into_response();
+            }
+        },
+    };
+
+    let today = chrono::Local::now().naive_local().date();
+    let inventory = state.inventory.read().unwrap();
+    let groups = frequencies
+        .iter()
+        .filter_map(|frequency| {
+            let items = inventory
+                .iter()
+                .filter(|t| t.subscription.as_deref() == Some(frequency))
+                .cloned()
+                .collect::<Vec<_>>();
+            let group = SubscriptionGroup {
+                next_billing_date: next_billing_date(frequency, today)?,
+                items,
+            };
+            (!group.items.is_empty()).then_some((frequency.to_string(), group))
+        })
+        .collect::<BTreeMap<_, _>>();
+
+    Json(groups).into_response()
+}
+
+pub async fn zone(State(state): State<AppState>, Path(zone): Path<String>) -> impl IntoResponse {
+    if !is_valid_availability_zone(&zone) {
+        return (StatusCode::BAD_REQUEST, "Invalid availability zone").into_response();
+    }
+
+    let toys = state
+        .inventory
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|t| t.is_available_in(&zone))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    Json(toys).into_response()
+}
+
+pub async fn supplier(State(state): State<AppState>, Path(id): Path<String>) -> impl IntoResponse {
+    let toys = state
+        .inventory
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|t| t.supplier_id.as_ref() == Some(&id))
+        .cloned()
+        .collect::<Vec<_>>();
+    if toys.is_empty() {
+        return (StatusCode::NOT_FOUND, "No items from this supplier").into_response();
+    }
+
+    Json(toys).into_response()
+}
+
+#[derive(serde::Serialize)]
+pub struct ToyMargin {
+    item: String,
+    margin_pct: f64,
+}
+
+pub async fn margin(State(state): State<AppState>) -> impl IntoResponse {
+    let mut margins = state
+        .inventory
+        .read()
+        .unwrap()
+        .iter()
+        .filter_map(|t| {
+            Some(ToyMargin {
+                item: t.item.clone(),
+                margin_pct: t.margin_pct()?,
+            })
+        })
+        .collect::<Vec<_>>();
+    margins.sort_by(|a, b| b.margin_pct.total_cmp(&a.margin_pct));
+
+    Json(margins)
+}
+
+const BUNDLE_DISCOUNT_PCT: f64 = 10.0;
+
+#[derive(serde::Deserialize)]
+pub struct BundleItem {
+    item: String,
+    qty: u32,
+}
+
+#[derive(serde::Deserialize)]
+pub struct BundleRequest {
+    items: Vec<BundleItem>,
+    discount_threshold: u32,
+}
+
+#[derive(serde::Serialize)]
+pub struct BundlePrice {
+    original_total: f64,
+    discounted_total: f64,
+    discount_applied: bool,
+}
+
+/// Total quantity requested per item, summed across repeated lines.
+fn requested_quantities(items: &[BundleItem]) -> HashMap<&str, u64> {
+    let mut requested = HashMap::new();
+    for wanted in items {
+        *requested.entry(wanted.item.as_str()).or_default() += u64::from(wanted.qty);
+    }
+
+    requested
+}
+
+pub async fn bundle_discount(
+    State(state): State<AppState>,
+    Json(request): Json<BundleRequest>,
+) -> impl IntoResponse {
+    let inventory = state.inventory.read().unwrap();
+    let requested = requested_quantities(&request.items);
+
+    let mut original_total = 0.0;
+    for wanted in &request.items {
+        let Some(toy) = inventory.iter().find(|t| t.item == wanted.item) else {
+            return (
+                StatusCode::NOT_FOUND,
+                format!("Unknown item: {}", wanted.item),
+            )
+                .into_response();
+        };
+        if u64::from(toy.quantity) < requested[wanted.item.as_str()] {
+            return (
+                StatusCode::CONFLICT,
+                format!("Insufficient stock for {}", wanted.item),
+            )
+                .into_response();
+        }
+        let Some(price) = toy.retail_price else {
+            return (
+                StatusCode::UNPROCESSABLE_ENTITY,
+                format!("No retail price for {}", wanted.item),
+            )
+                .into_response();
+        };
+        original_total += price * f64::from(wanted.qty);
+    }
+
+    let total_qty = requested.values().sum::<u64>();
+    let discount_applied = total_qty >= u64::from(request.discount_threshold);
+    let discounted_total = if discount_applied {
+        original_total * (1.0 - BUNDLE_DISCOUNT_PCT / 100.0)
+    } else {
+        original_total
+    };
+
+    Json(BundlePrice {
+        original_total,
+        discounted_total,
+        discount_applied,
+    })
+    .into_response()
+}
+
+#[derive(serde::Deserialize)]
+pub struct GiftSetRequest {
+    set_name: String,
+    items: Vec<BundleItem>,
+}
+
+#[derive(serde::Serialize)]
+pub struct FeasibleGiftSet {
+    feasible: bool,
+    total_weight_grams: Option<f64>,
+}
+
+#[derive(serde::Serialize)]
+pub struct InfeasibleGiftSet {
+    feasible: bool,
+    missing_items: Vec<String>,
+    insufficient_items: Vec<String>,
+}
+
+pub async fn gift_set(
+    State(state): State<AppState>,
+    Json(request): Json<GiftSetRequest>,
+) -> impl IntoResponse {
+    if request.set_name.trim().is_empty() {
+        return (StatusCode::BAD_REQUEST, "Set name must not be empty").into_response();
+    }
+
+    let inventory = state.inventory.read().unwrap();
+    let requested = requested_quantities(&request.items);
+    let (mut missing_items, mut insufficient_items) = (Vec::new(), Vec::new());
+    // Stays Some only while every item has a known weight.
+    let mut total_weight = Some(0.0);
+    for wanted in &request.items {
+        match inventory.iter().find(|t| t.item == wanted.item) {
+            None => missing_items.push(wanted.item.clone()),
+            Some(toy) if u64::from(toy.quantity) < requested[wanted.item.as_str()] => {
+                if !insufficient_items.contains(&wanted.item) {
+                    insufficient_items.push(wanted.item.clone());
+                }
+            }
+            Some(toy) => {
+                total_weight = total_weight
+                    .zip(toy.weight_grams)
+                    .map(|(total, grams)| total + grams * f64::from(wanted.qty));
+            }
+        }
+    }
+
+    if !missing_items.is_empty() || !insufficient_items.is_empty() {
+        let check = InfeasibleGiftSet {
+            feasible: false,
+            missing_items,
+            insufficient_items,
+        };
+        return (StatusCode::UNPROCESSABLE_ENTITY, Json(check)).into_response();
+    }
+
+    Json(FeasibleGiftSet {
+        feasible: true,
+        total_weight_grams: total_weight,
+    })
+    .into_response()
+}
+
+#[derive(serde::Deserialize)]
+pub struct AgeParameters {
+    age: u8,
+}
+
+pub async fn age(State(state): State<AppState>, params: Query<AgeParameters>) -> impl IntoResponse {
+    let toys = state
+        .inventory
+        .read()
+        .unwrap()
+        .iter()
+        .filter(|t| t.suits_age(params.age))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    Json(toys)
+}
+
+pub async fn material_stats(State(state): State<AppState>) -> impl IntoResponse {
+    let inventory = state.inventory.read().unwrap();
+
+    let mut weighted = HashMap::<String, f64>::new();
+    let mut total_quantity = 0.0;
+    for toy in inventory.iter() {
+        let Some(composition) = &toy.composition else {
+            continue;
+        };
+        let quantity = f64::from(toy.quantity);
+        total_quantity += quantity;
+        for (material, pct) in composition {
+            *weighted.entry(material.clone()).or_default() += pct * quantity;
+        }
+    }
+
+    if total_quantity > 0.0 {
+        for pct in weighted.values_mut() {
+            *pct /= total_quantity;
+        }
+    }
+
+    Json(weighted)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(value: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(value, "%Y-%m-%d").unwrap()
+    }
+
+    #[test]
+    fn next_billing_date_starts_the_next_period() {
+        let cases = [
+            ("monthly", "2024-01-31", "2024-02-01"),
+            ("monthly", "2024-12-15", "2025-01-01"),
+            ("quarterly", "2024-01-01", "2024-04-01"),
+            ("quarterly", "2024-03-31", "2024-04-01"),
+            ("quarterly", "2024-11-20", "2025-01-01"),
+            ("annual", "2024-01-01", "2025-01-01"),
+            ("annual", "2024-12-31", "2025-01-01"),
+        ];
+
+        for (frequency, today, expected) in cases {
+            assert_eq!(
+                next_billing_date(frequency, date(today)),
+                Some(date(expected)),
+                "{frequency} from {today}"
+            );
+        }
+        assert_eq!(next_billing_date("weekly", date("2024-01-01")), None);
+    }
+
+    #[test]
+    fn requested_quantities_add_up_repeated_items() {
+        let items =
+            [("Train", u32::MAX), ("Doll", 1), ("Train", u32::MAX)].map(|(item, qty)| BundleItem {
+                item: item.to_string(),
+                qty,
+            });
+
+        let requested = requested_quantities(&items);
+        assert_eq!(requested["Train"], 2 * u64::from(u32::MAX));
+        assert_eq!(requested["Doll"], 1);
+    }
+}
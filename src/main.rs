@@ -1,6 +1,10 @@
+mod conversion;
 mod dangerous_open_internet;
+mod inventory;
 mod ridiculous_routing;
 
+use std::sync::{Arc, Mutex, RwLock};
+
 use axum::{
     Router,
     http::{StatusCode, header},
@@ -9,10 +13,36 @@ use axum::{
 };
 
 use crate::{
-    dangerous_open_internet::manifest,
-    ridiculous_routing::{dest, key, v6_dest, v6_key},
+    conversion::{convert_length, convert_volume, convert_weight},
+    dangerous_open_internet::{
+        StarredManifests, ValidToy, changelog_check, compare_manifest_versions, edition_check,
+        fetch_manifest, lint, list_stars, manifest, manifest_repro_hash, star_manifest, starred,
+        suggest_manifest_edition, unstar_manifest,
+    },
+    inventory::{
+        age, bundle_discount, carbon_total, channel, components, discounted, fragile, gift_set,
+        high_risk, margin, material_stats, options, popular, ready, recycling, sets, shipping,
+        subscriptions, supplier, tax_summary, top_rated, units, zone,
+    },
+    ridiculous_routing::{
+        DestHistory, RoutingTable, add_route, arpa, chain, compare, deaggregate, deobfuscate, dest,
+        ecmp, fragment_offset, hierarchy, history, is_broadcast, key, list_routes, lookup_route,
+        mask, mcast_mac, merge, nat, nat64, nat64_reverse, obfuscate, ping, remove_route, reverse,
+        rotate, utilization_summary, v6_add, v6_and, v6_arpa, v6_canonical, v6_cidr_dest,
+        v6_compare, v6_delegation_tree, v6_dest, v6_from_seed_addr, v6_key, v6_mac, v6_mapped_dest,
+        v6_mcast_scope, v6_or, v6_reverse, v6_siit, v6_siit_extract, v6_sort, v6_strip_zone,
+        v6_ula,
+    },
 };
 
+#[derive(Clone, Default)]
+pub struct AppState {
+    pub routing_table: Arc<RwLock<RoutingTable>>,
+    pub inventory: Arc<RwLock<Vec<ValidToy>>>,
+    pub stars: Arc<RwLock<StarredManifests>>,
+    pub dest_history: Arc<Mutex<DestHistory>>,
+}
+
 async fn hello_world() -> &'static str {
     "Hello, bird!"
 }
@@ -34,10 +64,101 @@ async fn main() -> shuttle_axum::ShuttleAxum {
         .route("/", get(hello_world))
         .route("/-1/seek", get(seek))
         .route("/2/dest", get(dest))
+        .route("/2/history", get(history))
         .route("/2/key", get(key))
+        .route("/2/chain", post(chain))
+        .route("/2/fragment", post(fragment_offset))
+        .route("/2/rotate", get(rotate))
+        .route("/2/reverse", get(reverse))
+        .route("/2/mcast-mac", get(mcast_mac))
         .route("/2/v6/dest", get(v6_dest))
+        .route("/2/v6/add", get(v6_add))
+        .route("/2/v6/and", get(v6_and))
+        .route("/2/v6/or", get(v6_or))
         .route("/2/v6/key", get(v6_key))
-        .route("/5/manifest", post(manifest));
+        .route("/2/v6/mapped-dest", get(v6_mapped_dest))
+        .route("/2/v6/cidr-dest", get(v6_cidr_dest))
+        .route("/2/v6/mcast-scope", get(v6_mcast_scope))
+        .route("/2/v6/sort", post(v6_sort))
+        .route("/2/v6/delegation-tree", post(v6_delegation_tree))
+        .route("/2/v6/ula", get(v6_ula))
+        .route("/2/v6/mac", get(v6_mac))
+        .route("/2/v6/arpa", get(v6_arpa))
+        .route("/2/v6/nibble", get(v6_arpa))
+        .route("/2/v6/canonical", get(v6_canonical))
+        .route("/2/v6/strip-zone", get(v6_strip_zone))
+        .route("/2/v6/from-seed", get(v6_from_seed_addr))
+        .route("/2/v6/compare", get(v6_compare))
+        .route("/2/v6/reverse", get(v6_reverse))
+        .route("/2/v6/siit", get(v6_siit))
+        .route("/2/v6/siit-extract", get(v6_siit_extract))
+        .route(
+            "/2/routes",
+            get(list_routes).post(add_route).delete(remove_route),
+        )
+        .route("/2/routes/lookup", get(lookup_route))
+        .route("/2/hierarchy", get(hierarchy))
+        .route("/2/deaggregate", get(deaggregate))
+        .route("/2/mask", get(mask))
+        .route("/2/compare", get(compare))
+        .route("/2/is-broadcast", get(is_broadcast))
+        .route("/2/arpa", get(arpa))
+        .route("/2/merge", post(merge))
+        .route("/2/utilization", post(utilization_summary))
+        .route("/2/nat", post(nat))
+        .route("/2/ecmp", post(ecmp))
+        .route("/2/nat64", get(nat64))
+        .route("/2/nat64/reverse", get(nat64_reverse))
+        .route("/2/ping", get(ping))
+        .route("/2/obfuscate", get(obfuscate))
+        .route("/2/deobfuscate", get(deobfuscate))
+        .route("/5/manifest", post(manifest))
+        .route("/5/manifest/edition-check", post(edition_check))
+        .route(
+            "/5/manifest/suggest-edition",
+            post(suggest_manifest_edition),
+        )
+        .route("/5/manifest/lint", post(lint))
+        .route("/5/manifest/changelog-check", post(changelog_check))
+        .route("/5/manifest/fetch", post(fetch_manifest))
+        .route("/5/manifest/repro-hash", post(manifest_repro_hash))
+        .route(
+            "/5/manifest/star",
+            post(star_manifest).delete(unstar_manifest),
+        )
+        .route("/5/manifest/stars", get(list_stars))
+        .route("/5/manifest/starred", get(starred))
+        .route(
+            "/5/manifest/compare-versions",
+            post(compare_manifest_versions),
+        )
+        .route("/5/inventory/high-risk", get(high_risk))
+        .route("/5/inventory/shipping", get(shipping))
+        .route("/5/inventory/age", get(age))
+        .route("/5/inventory/material-stats", get(material_stats))
+        .route("/5/inventory/units", get(units))
+        .route("/5/inventory/components", get(components))
+        .route("/5/inventory/discounted", get(discounted))
+        .route("/5/inventory/ready", get(ready))
+        .route("/5/inventory/popular", get(popular))
+        .route("/5/inventory/sets", get(sets))
+        .route("/5/inventory/tax-summary", get(tax_summary))
+        .route("/5/inventory/recycling", get(recycling))
+        .route("/5/inventory/carbon-total", get(carbon_total))
+        .route("/5/inventory/options", get(options))
+        .route("/5/inventory/subscriptions", get(subscriptions))
+        .route("/5/inventory/channel/:channel", get(channel))
+        .route("/5/inventory/supplier/:id", get(supplier))
+        .route("/5/inventory/zone/:az", get(zone))
+        .route("/5/inventory/margin", get(margin))
+        .route("/5/inventory/fragile", get(fragile))
+        .route("/5/inventory/top-rated", get(top_rated))
+        .route("/5/inventory/bundle-discount", post(bundle_discount))
+        .route("/5/inventory/gift-set", post(gift_set))
+        .route("/5/convert/weight", get(convert_weight))
+        .route("/5/convert/length", get(convert_length))
+        .route("/5/convert/volume", get(convert_volume))
+        .with_state(AppState::default());
 
     Ok(router.into())
 }
@@ -1,6 +1,21 @@
-use std::net::{Ipv4Addr, Ipv6Addr};
+use std::{
+    cmp::Ordering,
+    collections::BTreeMap,
+    net::{Ipv4Addr, Ipv6Addr},
+    num::NonZeroUsize,
+};
 
-use axum::{extract::Query, response::IntoResponse};
+use axum::{
+    Json,
+    extract::{Query, State},
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use chrono::{DateTime, Utc};
+use lru::LruCache;
+use sha2::{Digest, Sha256};
+
+use crate::AppState;
 
 #[derive(serde::Deserialize)]
 pub struct DestParameters {
@@ -8,17 +23,124 @@ pub struct DestParameters {
     key: Ipv4Addr,
 }
 
-pub async fn dest(params: Query<DestParameters>) -> impl IntoResponse {
-    let params = params.0;
-    let from = params.from.octets();
-    let key = params.key.octets();
+fn apply_key(from: Ipv4Addr, key: Ipv4Addr) -> Ipv4Addr {
+    let from = from.octets();
+    let key = key.octets();
 
     let mut res: [u8; 4] = [0; 4];
     for i in 0..4 {
         res[i] = from[i].overflowing_add(key[i]).0;
     }
 
-    Ipv4Addr::from(res).to_string()
+    Ipv4Addr::from(res)
+}
+
+fn xor_v4(from: Ipv4Addr, key: Ipv4Addr) -> Ipv4Addr {
+    let from = from.octets();
+    let key = key.octets();
+
+    let mut res: [u8; 4] = [0; 4];
+    for i in 0..4 {
+        res[i] = from[i] ^ key[i];
+    }
+
+    Ipv4Addr::from(res)
+}
+
+const DEST_HISTORY_SOURCES: usize = 1000;
+const DEST_HISTORY_PER_SOURCE: usize = 1000;
+const DEST_HISTORY_SHOWN: usize = 10;
+
+/// Keys used with `/2/dest`, per source address. The least recently seen
+/// sources are forgotten first.
+pub struct DestHistory(LruCache<Ipv4Addr, Vec<(Ipv4Addr, DateTime<Utc>)>>);
+
+impl Default for DestHistory {
+    fn default() -> Self {
+        Self(LruCache::new(
+            NonZeroUsize::new(DEST_HISTORY_SOURCES).unwrap(),
+        ))
+    }
+}
+
+impl DestHistory {
+    fn record(&mut self, from: Ipv4Addr, key: Ipv4Addr) {
+        let history = self.0.get_or_insert_mut(from, Vec::new);
+        if history.len() == DEST_HISTORY_PER_SOURCE {
+            history.remove(0);
+        }
+        history.push((key, Utc::now()));
+    }
+}
+
+pub async fn dest(
+    State(state): State<AppState>,
+    params: Query<DestParameters>,
+) -> impl IntoResponse {
+    let params = params.0;
+    state
+        .dest_history
+        .lock()
+        .unwrap()
+        .record(params.from, params.key);
+
+    apply_key(params.from, params.key).to_string()
+}
+
+#[derive(serde::Deserialize)]
+pub struct HistoryParameters {
+    from: Ipv4Addr,
+}
+
+#[derive(serde::Serialize)]
+pub struct HistoryEntry {
+    key: Ipv4Addr,
+    dest: Ipv4Addr,
+    timestamp: DateTime<Utc>,
+}
+
+pub async fn history(
+    State(state): State<AppState>,
+    params: Query<HistoryParameters>,
+) -> impl IntoResponse {
+    let mut dest_history = state.dest_history.lock().unwrap();
+    let Some(history) = dest_history.0.get(&params.from) else {
+        return (StatusCode::NOT_FOUND, "No history for this address").into_response();
+    };
+
+    let entries = history[history.len().saturating_sub(DEST_HISTORY_SHOWN)..]
+        .iter()
+        .map(|&(key, timestamp)| HistoryEntry {
+            key,
+            dest: apply_key(params.from, key),
+            timestamp,
+        })
+        .collect::<Vec<_>>();
+
+    Json(entries).into_response()
+}
+
+#[derive(serde::Deserialize)]
+pub struct ChainParameters {
+    start: Ipv4Addr,
+    keys: Vec<Ipv4Addr>,
+}
+
+pub async fn chain(Json(params): Json<ChainParameters>) -> impl IntoResponse {
+    if !(1..=64).contains(&params.keys.len()) {
+        return (
+            StatusCode::BAD_REQUEST,
+            "Between 1 and 64 keys are required",
+        )
+            .into_response();
+    }
+
+    params
+        .keys
+        .into_iter()
+        .fold(params.start, apply_key)
+        .to_string()
+        .into_response()
 }
 
 #[derive(serde::Deserialize)]
@@ -45,16 +167,111 @@ pub struct V6DestParameters {
     key: Ipv6Addr,
 }
 
-pub async fn v6_dest(params: Query<V6DestParameters>) -> impl IntoResponse {
-    let params = params.0;
-    let from = params.from.octets();
-    let key = params.key.octets();
+fn xor_v6(from: Ipv6Addr, key: Ipv6Addr) -> Ipv6Addr {
+    let from = from.octets();
+    let key = key.octets();
     let mut res: [u8; 16] = [0; 16];
     for i in 0..res.len() {
         res[i] = from[i] ^ key[i];
     }
 
-    Ipv6Addr::from(res).to_string()
+    Ipv6Addr::from(res)
+}
+
+pub async fn v6_dest(params: Query<V6DestParameters>) -> impl IntoResponse {
+    let params = params.0;
+
+    xor_v6(params.from, params.key).to_string()
+}
+
+#[derive(serde::Deserialize)]
+pub struct V6BitwiseParameters {
+    a: Ipv6Addr,
+    b: Ipv6Addr,
+}
+
+fn and_v6(a: Ipv6Addr, b: Ipv6Addr) -> Ipv6Addr {
+    Ipv6Addr::from(u128::from(a) & u128::from(b))
+}
+
+fn or_v6(a: Ipv6Addr, b: Ipv6Addr) -> Ipv6Addr {
+    Ipv6Addr::from(u128::from(a) | u128::from(b))
+}
+
+pub async fn v6_and(params: Query<V6BitwiseParameters>) -> impl IntoResponse {
+    and_v6(params.a, params.b).to_string()
+}
+
+pub async fn v6_or(params: Query<V6BitwiseParameters>) -> impl IntoResponse {
+    or_v6(params.a, params.b).to_string()
+}
+
+fn default_wrap() -> bool {
+    true
+}
+
+#[derive(serde::Deserialize)]
+pub struct V6AddParameters {
+    from: Ipv6Addr,
+    n: u64,
+    #[serde(default = "default_wrap")]
+    wrap: bool,
+}
+
+/// Adds `n` to the address, or returns `None` if it overflows and `wrap` is off.
+fn add_v6(from: Ipv6Addr, n: u64, wrap: bool) -> Option<Ipv6Addr> {
+    match u128::from(from).overflowing_add(n.into()) {
+        (_, true) if !wrap => None,
+        (sum, _) => Some(sum.into()),
+    }
+}
+
+pub async fn v6_add(params: Query<V6AddParameters>) -> impl IntoResponse {
+    match add_v6(params.from, params.n, params.wrap) {
+        Some(addr) => addr.to_string().into_response(),
+        None => (StatusCode::UNPROCESSABLE_ENTITY, "Address overflow").into_response(),
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct V6MappedDestParameters {
+    from: Ipv4Addr,
+    key: Ipv4Addr,
+}
+
+pub async fn v6_mapped_dest(params: Query<V6MappedDestParameters>) -> impl IntoResponse {
+    let params = params.0;
+    let res = xor_v6(params.from.to_ipv6_mapped(), params.key.to_ipv6_mapped());
+
+    // Both operands carry the ::ffff: marker, so the XOR clears it and the
+    // result normally comes back as a plain IPv6 address.
+    match res.to_ipv4_mapped() {
+        Some(addr) => addr.to_string(),
+        None => res.to_string(),
+    }
+}
+
+/// Mask selecting the host bits that follow an IPv6 prefix of `prefix_len` bits.
+fn v6_host_mask(prefix_len: u8) -> u128 {
+    u128::MAX.checked_shr(prefix_len.into()).unwrap_or(0)
+}
+
+#[derive(serde::Deserialize)]
+pub struct V6CidrDestParameters {
+    from: Ipv6Addr,
+    prefix_len: u8,
+    key: Ipv6Addr,
+}
+
+pub async fn v6_cidr_dest(params: Query<V6CidrDestParameters>) -> impl IntoResponse {
+    if params.prefix_len > 128 {
+        return (StatusCode::BAD_REQUEST, "Prefix length must be at most 128").into_response();
+    }
+
+    let host_key = u128::from(params.key) & v6_host_mask(params.prefix_len);
+    Ipv6Addr::from(u128::from(params.from) ^ host_key)
+        .to_string()
+        .into_response()
 }
 
 #[derive(serde::Deserialize)]
@@ -74,3 +291,1707 @@ pub async fn v6_key(params: Query<V6KeyParameters>) -> impl IntoResponse {
 
     Ipv6Addr::from(res).to_string()
 }
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Deserialize, serde::Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Ipv4Cidr {
+    addr: Ipv4Addr,
+    prefix_len: u8,
+}
+
+fn prefix_mask(prefix_len: u8) -> u32 {
+    match prefix_len {
+        0 => 0,
+        len => u32::MAX << (32 - len),
+    }
+}
+
+impl Ipv4Cidr {
+    fn new(network: u32, prefix_len: u8) -> Self {
+        Self {
+            addr: Ipv4Addr::from(network & prefix_mask(prefix_len)),
+            prefix_len,
+        }
+    }
+
+    fn mask(&self) -> u32 {
+        prefix_mask(self.prefix_len)
+    }
+
+    fn network(&self) -> u32 {
+        u32::from(self.addr) & self.mask()
+    }
+
+    /// First and one-past-last address, widened so a /0 doesn't overflow.
+    fn range(&self) -> (u64, u64) {
+        let start = u64::from(self.network());
+        (start, start + (1u64 << (32 - self.prefix_len)))
+    }
+}
+
+impl TryFrom<String> for Ipv4Cidr {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let (addr, prefix_len) = value
+            .split_once('/')
+            .ok_or_else(|| format!("Missing prefix length in {value}"))?;
+        let addr = addr
+            .parse::<Ipv4Addr>()
+            .map_err(|_| format!("Invalid address in {value}"))?;
+        let prefix_len = match prefix_len.parse::<u8>() {
+            Ok(len) if len <= 32 => len,
+            _ => return Err(format!("Invalid prefix length in {value}")),
+        };
+        Ok(Self { addr, prefix_len })
+    }
+}
+
+impl std::fmt::Display for Ipv4Cidr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/{}", self.addr, self.prefix_len)
+    }
+}
+
+impl From<Ipv4Cidr> for String {
+    fn from(value: Ipv4Cidr) -> Self {
+        value.to_string()
+    }
+}
+
+/// Static routes grouped by prefix length so lookups can walk from the most
+/// specific prefix down and stop at the first match.
+#[derive(Default)]
+pub struct RoutingTable {
+    routes: BTreeMap<u8, Vec<(u32, u32)>>,
+}
+
+impl RoutingTable {
+    fn insert(&mut self, prefix: Ipv4Cidr, nexthop: Ipv4Addr) {
+        let network = prefix.network();
+        let routes = self.routes.entry(prefix.prefix_len).or_default();
+        match routes.iter_mut().find(|(n, _)| *n == network) {
+            Some(route) => route.1 = nexthop.into(),
+            None => routes.push((network, nexthop.into())),
+        }
+    }
+
+    fn remove(&mut self, prefix: Ipv4Cidr) -> bool {
+        let network = prefix.network();
+        let Some(routes) = self.routes.get_mut(&prefix.prefix_len) else {
+            return false;
+        };
+        let len = routes.len();
+        routes.retain(|(n, _)| *n != network);
+        let removed = routes.len() != len;
+        if routes.is_empty() {
+            self.routes.remove(&prefix.prefix_len);
+        }
+        removed
+    }
+
+    fn lookup(&self, addr: Ipv4Addr) -> Option<Ipv4Addr> {
+        let addr = u32::from(addr);
+        self.routes.iter().rev().find_map(|(&prefix_len, routes)| {
+            let mask = prefix_mask(prefix_len);
+            routes
+                .iter()
+                .find(|(network, _)| addr & mask == *network)
+                .map(|(_, nexthop)| Ipv4Addr::from(*nexthop))
+        })
+    }
+
+    fn routes(&self) -> Vec<(u32, u8, Ipv4Addr)> {
+        self.routes
+            .iter()
+            .rev()
+            .flat_map(|(&prefix_len, routes)| {
+                routes
+                    .iter()
+                    .map(move |&(network, nexthop)| (network, prefix_len, nexthop.into()))
+            })
+            .collect()
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct Route {
+    prefix: String,
+    nexthop: Ipv4Addr,
+}
+
+#[derive(serde::Deserialize)]
+pub struct AddRouteParameters {
+    prefix: Ipv4Cidr,
+    nexthop: Ipv4Addr,
+}
+
+pub async fn add_route(
+    State(state): State<AppState>,
+    Json(params): Json<AddRouteParameters>,
+) -> impl IntoResponse {
+    state
+        .routing_table
+        .write()
+        .unwrap()
+        .insert(params.prefix, params.nexthop);
+
+    StatusCode::CREATED
+}
+
+#[derive(serde::Deserialize)]
+pub struct RemoveRouteParameters {
+    prefix: Ipv4Cidr,
+}
+
+pub async fn remove_route(
+    State(state): State<AppState>,
+    Json(params): Json<RemoveRouteParameters>,
+) -> impl IntoResponse {
+    if state.routing_table.write().unwrap().remove(params.prefix) {
+        StatusCode::NO_CONTENT
+    } else {
+        StatusCode::NOT_FOUND
+    }
+}
+
+pub async fn list_routes(State(state): State<AppState>) -> impl IntoResponse {
+    let routes = state
+        .routing_table
+        .read()
+        .unwrap()
+        .routes()
+        .into_iter()
+        .map(|(network, prefix_len, nexthop)| Route {
+            prefix: format!("{}/{}", Ipv4Addr::from(network), prefix_len),
+            nexthop,
+        })
+        .collect::<Vec<_>>();
+
+    Json(routes)
+}
+
+#[derive(serde::Deserialize)]
+pub struct LookupParameters {
+    addr: Ipv4Addr,
+}
+
+pub async fn lookup_route(
+    State(state): State<AppState>,
+    params: Query<LookupParameters>,
+) -> impl IntoResponse {
+    match state.routing_table.read().unwrap().lookup(params.addr) {
+        Some(nexthop) => nexthop.to_string().into_response(),
+        None => (StatusCode::NOT_FOUND, "No matching route").into_response(),
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct V6McastScopeParameters {
+    addr: Ipv6Addr,
+}
+
+#[derive(serde::Serialize)]
+pub struct MulticastScope {
+    scope_id: u8,
+    scope_name: &'static str,
+}
+
+pub async fn v6_mcast_scope(params: Query<V6McastScopeParameters>) -> impl IntoResponse {
+    let octets = params.addr.octets();
+    if octets[0] != 0xff {
+        return (StatusCode::BAD_REQUEST, "Not a multicast address").into_response();
+    }
+
+    let scope_id = octets[1] & 0x0f;
+    let scope_name = match scope_id {
+        1 => "interface-local",
+        2 => "link-local",
+        4 => "admin-local",
+        5 => "site-local",
+        8 => "organization-local",
+        14 => "global",
+        0 | 15 => "reserved",
+        _ => "unassigned",
+    };
+
+    Json(MulticastScope {
+        scope_id,
+        scope_name,
+    })
+    .into_response()
+}
+
+#[derive(serde::Deserialize, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    #[default]
+    Asc,
+    Desc,
+}
+
+#[derive(serde::Deserialize)]
+pub struct V6SortParameters {
+    #[serde(default)]
+    order: SortOrder,
+}
+
+fn sort_v6(addrs: &[String], order: &SortOrder) -> Result<Vec<Ipv6Addr>, String> {
+    let mut sorted = addrs
+        .iter()
+        .map(|addr| {
+            addr.parse::<Ipv6Addr>()
+                .map_err(|_| format!("Invalid IPv6 address: {addr}"))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    sorted.sort_by_key(|addr| u128::from(*addr));
+    if *order == SortOrder::Desc {
+        sorted.reverse();
+    }
+
+    Ok(sorted)
+}
+
+pub async fn v6_sort(
+    params: Query<V6SortParameters>,
+    Json(addrs): Json<Vec<String>>,
+) -> impl IntoResponse {
+    match sort_v6(&addrs, &params.order) {
+        Ok(sorted) => Json(sorted).into_response(),
+        Err(message) => (StatusCode::BAD_REQUEST, message).into_response(),
+    }
+}
+
+const IPV4_HEADER_LEN: u32 = 20;
+const IPV4_MIN_MTU: u32 = 68;
+
+#[derive(serde::Deserialize)]
+pub struct FragmentParameters {
+    payload_size: u32,
+    mtu: u32,
+    fragment_index: u32,
+}
+
+#[derive(serde::Serialize, Debug, PartialEq)]
+pub struct Fragment {
+    /// Offset of the fragment's data in 8-byte units.
+    offset: u32,
+    /// Number of payload bytes carried, excluding the IPv4 header.
+    size: u32,
+    more_fragments: bool,
+}
+
+fn fragment(params: &FragmentParameters) -> Result<Fragment, &'static str> {
+    if params.payload_size == 0 {
+        return Err("Payload size must be positive");
+    }
+    if params.mtu < IPV4_MIN_MTU {
+        return Err("MTU is below the IPv4 minimum of 68");
+    }
+
+    // Every fragment but the last must carry a multiple of 8 bytes.
+    let max_size = (params.mtu - IPV4_HEADER_LEN) / 8 * 8;
+    let count = params.payload_size.div_ceil(max_size);
+    if params.fragment_index >= count {
+        return Err("Fragment index exceeds the fragment count");
+    }
+
+    let start = params.fragment_index * max_size;
+    Ok(Fragment {
+        offset: start / 8,
+        size: max_size.min(params.payload_size - start),
+        more_fragments: params.fragment_index + 1 < count,
+    })
+}
+
+pub async fn fragment_offset(Json(params): Json<FragmentParameters>) -> impl IntoResponse {
+    match fragment(&params) {
+        Ok(fragment) => Json(fragment).into_response(),
+        Err(message) => (StatusCode::BAD_REQUEST, message).into_response(),
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct V6UlaParameters {
+    addr: Ipv6Addr,
+}
+
+#[derive(serde::Serialize, Debug, PartialEq)]
+pub struct UlaClassification {
+    is_ula: bool,
+    locally_assigned: bool,
+}
+
+fn classify_ula(addr: Ipv6Addr) -> UlaClassification {
+    let first = addr.octets()[0];
+    let is_ula = first & 0xfe == 0xfc;
+
+    UlaClassification {
+        is_ula,
+        // RFC 4193 encodes the L bit as the eighth bit of the prefix.
+        locally_assigned: is_ula && first & 0x01 == 0x01,
+    }
+}
+
+pub async fn v6_ula(params: Query<V6UlaParameters>) -> impl IntoResponse {
+    Json(classify_ula(params.addr))
+}
+
+#[derive(serde::Deserialize)]
+pub struct ReverseParameters<T> {
+    addr: T,
+}
+
+fn reverse_v4(addr: Ipv4Addr) -> Ipv4Addr {
+    let mut octets = addr.octets();
+    octets.reverse();
+    Ipv4Addr::from(octets)
+}
+
+fn reverse_v6(addr: Ipv6Addr) -> Ipv6Addr {
+    let mut octets = addr.octets();
+    octets.reverse();
+    Ipv6Addr::from(octets)
+}
+
+pub async fn reverse(params: Query<ReverseParameters<Ipv4Addr>>) -> impl IntoResponse {
+    reverse_v4(params.addr).to_string()
+}
+
+pub async fn v6_reverse(params: Query<ReverseParameters<Ipv6Addr>>) -> impl IntoResponse {
+    reverse_v6(params.addr).to_string()
+}
+
+#[derive(serde::Deserialize)]
+pub struct RotateParameters {
+    addr: Ipv4Addr,
+    bits: i8,
+}
+
+fn rotate_addr(addr: Ipv4Addr, bits: i8) -> Ipv4Addr {
+    let addr = u32::from(addr);
+    let bits = bits.clamp(-31, 31);
+
+    if bits >= 0 {
+        addr.rotate_left(bits.unsigned_abs().into())
+    } else {
+        addr.rotate_right(bits.unsigned_abs().into())
+    }
+    .into()
+}
+
+pub async fn rotate(params: Query<RotateParameters>) -> impl IntoResponse {
+    rotate_addr(params.addr, params.bits).to_string()
+}
+
+#[derive(serde::Deserialize)]
+pub struct McastMacParameters {
+    addr: Ipv4Addr,
+}
+
+#[derive(serde::Serialize)]
+pub struct MacAddress {
+    mac: String,
+}
+
+pub async fn mcast_mac(params: Query<McastMacParameters>) -> impl IntoResponse {
+    if !params.addr.is_multicast() {
+        return (StatusCode::BAD_REQUEST, "Not a multicast address").into_response();
+    }
+
+    // RFC 1112 maps the low-order 23 bits of the group into 01:00:5e:00:00:00.
+    let [_, b, c, d] = params.addr.octets();
+    let mac = format!("01:00:5e:{:02x}:{c:02x}:{d:02x}", b & 0x7f);
+
+    Json(MacAddress { mac }).into_response()
+}
+
+#[derive(serde::Deserialize)]
+pub struct HierarchyParameters {
+    prefix: Ipv4Cidr,
+}
+
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct SubnetHierarchy {
+    parent: Option<Ipv4Cidr>,
+    sibling: Option<Ipv4Cidr>,
+    children: Option<[Ipv4Cidr; 2]>,
+}
+
+fn subnet_hierarchy(prefix: Ipv4Cidr) -> SubnetHierarchy {
+    let network = prefix.network();
+    let len = prefix.prefix_len;
+
+    // The bit just past the prefix separates the two halves of a subnet.
+    let half_bit = |len: u8| 1u32 << (32 - len);
+
+    let (parent, sibling) = match len {
+        0 => (None, None),
+        len => (
+            Some(Ipv4Cidr::new(network, len - 1)),
+            Some(Ipv4Cidr::new(network ^ half_bit(len), len)),
+        ),
+    };
+    let children = match len {
+        32 => None,
+        len => Some([
+            Ipv4Cidr::new(network, len + 1),
+            Ipv4Cidr::new(network | half_bit(len + 1), len + 1),
+        ]),
+    };
+
+    SubnetHierarchy {
+        parent,
+        sibling,
+        children,
+    }
+}
+
+pub async fn hierarchy(params: Query<HierarchyParameters>) -> impl IntoResponse {
+    Json(subnet_hierarchy(params.prefix))
+}
+
+const DEAGGREGATE_LIMIT: u64 = 1024;
+const DEAGGREGATE_LARGE_LIMIT: u64 = 4096;
+
+#[derive(serde::Deserialize)]
+pub struct DeaggregateParameters {
+    prefix: Ipv4Cidr,
+    size: u8,
+    #[serde(default)]
+    allow_large: bool,
+}
+
+fn deaggregate_prefix(prefix: Ipv4Cidr, size: u8, limit: u64) -> Result<Vec<Ipv4Cidr>, String> {
+    if size <= prefix.prefix_len || size > 32 {
+        return Err(format!(
+            "Size must be between /{} and /32",
+            prefix.prefix_len + 1
+        ));
+    }
+
+    let count = 1u64 << (size - prefix.prefix_len);
+    if count > limit {
+        return Err(format!(
+            "Splitting into {count} prefixes exceeds the limit of {limit}"
+        ));
+    }
+
+    let (start, _) = prefix.range();
+    let step = 1u64 << (32 - size);
+    Ok((0..count)
+        .map(|i| Ipv4Cidr::new((start + i * step) as u32, size))
+        .collect())
+}
+
+pub async fn deaggregate(params: Query<DeaggregateParameters>) -> impl IntoResponse {
+    let limit = if params.allow_large {
+        DEAGGREGATE_LARGE_LIMIT
+    } else {
+        DEAGGREGATE_LIMIT
+    };
+
+    match deaggregate_prefix(params.prefix, params.size, limit) {
+        Ok(prefixes) => Json(prefixes).into_response(),
+        Err(err) => (StatusCode::BAD_REQUEST, err).into_response(),
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct MergedSubnet {
+    merged: Ipv4Cidr,
+}
+
+/// Merges two subnets into their shared parent, provided they are its halves.
+fn merge_subnets(a: Ipv4Cidr, b: Ipv4Cidr) -> Result<Ipv4Cidr, String> {
+    if a.prefix_len != b.prefix_len {
+        return Err(format!(
+            "{a} and {b} are different sizes (/{} vs /{})",
+            a.prefix_len, b.prefix_len
+        ));
+    }
+    if a.prefix_len == 0 {
+        return Err("A /0 has no parent to merge into".to_string());
+    }
+
+    let (low, high) = if a.network() <= b.network() {
+        (a, b)
+    } else {
+        (b, a)
+    };
+    if low.range().1 != high.range().0 {
+        return Err(format!("{a} and {b} are not adjacent"));
+    }
+
+    let parent_len = a.prefix_len - 1;
+    let parent = Ipv4Cidr::new(low.network(), parent_len);
+    if parent.network() != high.network() & prefix_mask(parent_len) {
+        return Err(format!(
+            "{a} and {b} are adjacent but belong to different /{parent_len} supernets"
+        ));
+    }
+
+    Ok(parent)
+}
+
+pub async fn merge(Json(subnets): Json<Vec<Ipv4Cidr>>) -> impl IntoResponse {
+    let [a, b] = subnets[..] else {
+        return (StatusCode::BAD_REQUEST, "Expected exactly two subnets").into_response();
+    };
+
+    match merge_subnets(a, b) {
+        Ok(merged) => Json(MergedSubnet { merged }).into_response(),
+        Err(err) => (StatusCode::BAD_REQUEST, err).into_response(),
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct UtilizationRequest {
+    allocated: Vec<Ipv4Cidr>,
+    total: Ipv4Cidr,
+}
+
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct Utilization {
+    used_addresses: u64,
+    total_addresses: u64,
+    utilization_pct: f64,
+}
+
+fn utilization(allocated: &[Ipv4Cidr], total: Ipv4Cidr) -> Result<Utilization, String> {
+    let (total_start, total_end) = total.range();
+
+    let mut ranges = allocated
+        .iter()
+        .map(|cidr| {
+            let (start, end) = cidr.range();
+            if start < total_start || end > total_end {
+                return Err(format!("{cidr} is outside {total}"));
+            }
+            Ok((start, end))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    ranges.sort_unstable();
+
+    // Merge overlapping ranges so shared addresses are only counted once.
+    let mut used_addresses = 0;
+    let mut covered_until = 0;
+    for (start, end) in ranges {
+        let start = start.max(covered_until);
+        if end > start {
+            used_addresses += end - start;
+            covered_until = end;
+        }
+    }
+
+    let total_addresses = total_end - total_start;
+    Ok(Utilization {
+        used_addresses,
+        total_addresses,
+        utilization_pct: used_addresses as f64 / total_addresses as f64 * 100.0,
+    })
+}
+
+pub async fn utilization_summary(Json(request): Json<UtilizationRequest>) -> impl IntoResponse {
+    match utilization(&request.allocated, request.total) {
+        Ok(summary) => Json(summary).into_response(),
+        Err(err) => (StatusCode::BAD_REQUEST, err).into_response(),
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct NatRequest {
+    private: Ipv4Addr,
+    public_pool: Ipv4Cidr,
+    seed: u32,
+}
+
+#[derive(serde::Serialize)]
+pub struct NatTranslation {
+    private: Ipv4Addr,
+    public: Ipv4Addr,
+}
+
+/// MurmurHash3's 32-bit finalizer; stable across Rust releases, unlike the
+/// std hashers.
+fn mix32(mut h: u32) -> u32 {
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x85eb_ca6b);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0xc2b2_ae35);
+    h ^ (h >> 16)
+}
+
+/// 32-bit FNV-1a.
+fn fnv1a(bytes: &[u8]) -> u32 {
+    bytes.iter().fold(0x811c_9dc5, |h, &b| {
+        (h ^ u32::from(b)).wrapping_mul(0x0100_0193)
+    })
+}
+
+#[derive(serde::Deserialize)]
+pub struct EcmpParameters {
+    addr: Ipv4Addr,
+}
+
+#[derive(serde::Deserialize)]
+pub struct EcmpPath {
+    nexthop: Ipv4Addr,
+    weight: u32,
+}
+
+#[derive(serde::Deserialize)]
+pub struct EcmpRequest {
+    paths: Vec<EcmpPath>,
+}
+
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct EcmpSelection {
+    selected_nexthop: Ipv4Addr,
+    path_index: usize,
+    hash: String,
+}
+
+/// Picks a path with probability proportional to its weight, always the same
+/// one for a given destination.
+fn select_path(addr: Ipv4Addr, paths: &[EcmpPath]) -> Result<EcmpSelection, &'static str> {
+    if paths.is_empty() {
+        return Err("No paths given");
+    }
+    let total = paths.iter().map(|p| u64::from(p.weight)).sum::<u64>();
+    if total == 0 {
+        return Err("Paths must have a non-zero total weight");
+    }
+
+    let hash = fnv1a(&addr.octets());
+    let mut slot = u64::from(hash) % total;
+    let path_index = paths
+        .iter()
+        .position(|path| match slot.checked_sub(u64::from(path.weight)) {
+            Some(rest) => {
+                slot = rest;
+                false
+            }
+            None => true,
+        })
+        .unwrap();
+
+    Ok(EcmpSelection {
+        selected_nexthop: paths[path_index].nexthop,
+        path_index,
+        hash: format!("{hash:08x}"),
+    })
+}
+
+pub async fn ecmp(
+    params: Query<EcmpParameters>,
+    Json(request): Json<EcmpRequest>,
+) -> impl IntoResponse {
+    match select_path(params.addr, &request.paths) {
+        Ok(selection) => Json(selection).into_response(),
+        Err(err) => (StatusCode::BAD_REQUEST, err).into_response(),
+    }
+}
+
+fn nat_translate(private: Ipv4Addr, pool: Ipv4Cidr, seed: u32) -> Ipv4Addr {
+    let (start, end) = pool.range();
+    // Skip the network and broadcast addresses unless the pool is too small
+    // to have any (/31 and /32).
+    let (first_host, hosts) = match end - start {
+        size if size > 2 => (start + 1, size - 2),
+        size => (start, size),
+    };
+
+    let index = u64::from(mix32(u32::from(private) ^ seed)) % hosts;
+    Ipv4Addr::from((first_host + index) as u32)
+}
+
+pub async fn nat(Json(request): Json<NatRequest>) -> impl IntoResponse {
+    if !request.private.is_private() {
+        return (StatusCode::BAD_REQUEST, "Not an RFC 1918 private address").into_response();
+    }
+
+    Json(NatTranslation {
+        private: request.private,
+        public: nat_translate(request.private, request.public_pool, request.seed),
+    })
+    .into_response()
+}
+
+#[derive(serde::Deserialize)]
+pub struct Nat64Parameters {
+    addr: Ipv4Addr,
+    prefix: String,
+}
+
+#[derive(serde::Deserialize)]
+pub struct Nat64ReverseParameters {
+    addr: Ipv6Addr,
+    prefix: String,
+}
+
+/// Parses a `<IPv6>/96` NAT64 prefix into its network bits. Only the /96
+/// layout from RFC 6052 is supported, where the IPv4 address fills the last
+/// four octets.
+fn parse_nat64_prefix(prefix: &str) -> Result<u128, &'static str> {
+    let (addr, len) = prefix
+        .split_once('/')
+        .ok_or("Prefix must include a /96 length")?;
+    let addr = addr
+        .parse::<Ipv6Addr>()
+        .map_err(|_| "Invalid IPv6 prefix")?;
+    if len != "96" {
+        return Err("Prefix length must be 96");
+    }
+
+    Ok(u128::from(addr) & !u128::from(u32::MAX))
+}
+
+fn embed_v4(prefix: u128, addr: Ipv4Addr) -> Ipv6Addr {
+    Ipv6Addr::from(prefix | u128::from(u32::from(addr)))
+}
+
+fn extract_v4(prefix: u128, addr: Ipv6Addr) -> Option<Ipv4Addr> {
+    let addr = u128::from(addr);
+    (addr & !u128::from(u32::MAX) == prefix).then_some(Ipv4Addr::from(addr as u32))
+}
+
+fn embed_response(prefix: &str, addr: Ipv4Addr) -> Response {
+    match parse_nat64_prefix(prefix) {
+        Ok(prefix) => embed_v4(prefix, addr).to_string().into_response(),
+        Err(err) => (StatusCode::BAD_REQUEST, err).into_response(),
+    }
+}
+
+fn extract_response(prefix: &str, addr: Ipv6Addr) -> Response {
+    let prefix = match parse_nat64_prefix(prefix) {
+        Ok(prefix) => prefix,
+        Err(err) => return (StatusCode::BAD_REQUEST, err).into_response(),
+    };
+
+    match extract_v4(prefix, addr) {
+        Some(addr) => addr.to_string().into_response(),
+        None => (StatusCode::BAD_REQUEST, "Address is not within the prefix").into_response(),
+    }
+}
+
+pub async fn nat64(params: Query<Nat64Parameters>) -> impl IntoResponse {
+    embed_response(&params.prefix, params.addr)
+}
+
+pub async fn nat64_reverse(params: Query<Nat64ReverseParameters>) -> impl IntoResponse {
+    extract_response(&params.prefix, params.addr)
+}
+
+#[derive(serde::Deserialize)]
+pub struct SiitParameters {
+    ipv4: Ipv4Addr,
+    prefix: String,
+}
+
+#[derive(serde::Deserialize)]
+pub struct SiitExtractParameters {
+    ipv6: Ipv6Addr,
+    prefix: String,
+}
+
+/// RFC 6145 translation uses the same /96 embedding as NAT64.
+pub async fn v6_siit(params: Query<SiitParameters>) -> impl IntoResponse {
+    embed_response(&params.prefix, params.ipv4)
+}
+
+pub async fn v6_siit_extract(params: Query<SiitExtractParameters>) -> impl IntoResponse {
+    extract_response(&params.prefix, params.ipv6)
+}
+
+const DELEGATION_TREE_MAX_NODES: u64 = 256;
+
+#[derive(serde::Deserialize)]
+pub struct DelegationTreeRequest {
+    root: String,
+    levels: Vec<u8>,
+}
+
+#[derive(serde::Serialize)]
+pub struct DelegationNode {
+    prefix: String,
+    children: Vec<DelegationNode>,
+}
+
+fn parse_v6_cidr(value: &str) -> Result<(u128, u8), String> {
+    let (addr, len) = value
+        .split_once('/')
+        .ok_or_else(|| format!("Missing prefix length in {value}"))?;
+    let addr = addr
+        .parse::<Ipv6Addr>()
+        .map_err(|_| format!("Invalid address in {value}"))?;
+    match len.parse::<u8>() {
+        Ok(len) if len <= 128 => Ok((u128::from(addr) & !v6_host_mask(len), len)),
+        _ => Err(format!("Invalid prefix length in {value}")),
+    }
+}
+
+fn delegate(network: u128, len: u8, levels: &[u8]) -> DelegationNode {
+    let children = match levels.split_first() {
+        None => Vec::new(),
+        Some((&bits, rest)) => {
+            let child_len = len + bits;
+            (0..1u128 << bits)
+                .map(|i| {
+                    // Only a zero-bit level under ::/0 shifts by 128, and its sole
+                    // child has no offset.
+                    let offset = i.checked_shl(u32::from(128 - child_len)).unwrap_or(0);
+                    delegate(network | offset, child_len, rest)
+                })
+                .collect()
+        }
+    };
+
+    DelegationNode {
+        prefix: format!("{}/{len}", Ipv6Addr::from(network)),
+        children,
+    }
+}
+
+fn delegation_tree(root: &str, levels: &[u8]) -> Result<DelegationNode, String> {
+    let (network, len) = parse_v6_cidr(root)?;
+
+    let total_len = levels
+        .iter()
+        .fold(u32::from(len), |total, bits| total + u32::from(*bits));
+    if total_len > 128 {
+        return Err(format!("Levels would produce a /{total_len} prefix"));
+    }
+
+    let mut nodes = 1u64;
+    let mut width = 1u64;
+    for bits in levels {
+        width = 1u64
+            .checked_shl(u32::from(*bits))
+            .and_then(|children| width.checked_mul(children))
+            .unwrap_or(u64::MAX);
+        nodes = nodes.saturating_add(width);
+    }
+    if nodes > DELEGATION_TREE_MAX_NODES {
+        return Err(format!(
+            "Tree would exceed {DELEGATION_TREE_MAX_NODES} nodes"
+        ));
+    }
+
+    Ok(delegate(network, len, levels))
+}
+
+pub async fn v6_delegation_tree(Json(request): Json<DelegationTreeRequest>) -> impl IntoResponse {
+    match delegation_tree(&request.root, &request.levels) {
+        Ok(tree) => Json(tree).into_response(),
+        Err(err) => (StatusCode::BAD_REQUEST, err).into_response(),
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct V6CanonicalParameters {
+    addr: String,
+}
+
+#[derive(serde::Serialize)]
+pub struct V6Canonical {
+    canonical: String,
+    was_canonical: bool,
+    input: String,
+}
+
+/// Reports the RFC 5952 form of an address and whether the input already
+/// used it byte for byte.
+pub async fn v6_canonical(params: Query<V6CanonicalParameters>) -> impl IntoResponse {
+    let Ok(addr) = params.addr.parse::<Ipv6Addr>() else {
+        return (StatusCode::BAD_REQUEST, "Invalid IPv6 address").into_response();
+    };
+
+    let canonical = addr.to_string();
+    Json(V6Canonical {
+        was_canonical: canonical == params.addr,
+        canonical,
+        input: params.0.addr,
+    })
+    .into_response()
+}
+
+#[derive(serde::Deserialize)]
+pub struct CompareParameters<T> {
+    a: T,
+    b: T,
+}
+
+fn ordering_name(ordering: Ordering) -> &'static str {
+    match ordering {
+        Ordering::Less => "less",
+        Ordering::Equal => "equal",
+        Ordering::Greater => "greater",
+    }
+}
+
+#[derive(serde::Serialize)]
+pub struct V4Comparison {
+    result: &'static str,
+    #[serde(rename = "same_/24")]
+    same_24: bool,
+}
+
+#[derive(serde::Serialize)]
+pub struct V6Comparison {
+    result: &'static str,
+}
+
+pub async fn compare(params: Query<CompareParameters<Ipv4Addr>>) -> impl IntoResponse {
+    let (a, b) = (u32::from(params.a), u32::from(params.b));
+
+    Json(V4Comparison {
+        result: ordering_name(a.cmp(&b)),
+        same_24: a & prefix_mask(24) == b & prefix_mask(24),
+    })
+}
+
+pub async fn v6_compare(params: Query<CompareParameters<Ipv6Addr>>) -> impl IntoResponse {
+    Json(V6Comparison {
+        result: ordering_name(u128::from(params.a).cmp(&u128::from(params.b))),
+    })
+}
+
+#[derive(serde::Deserialize)]
+pub struct V6StripZoneParameters {
+    addr: String,
+}
+
+#[derive(serde::Serialize)]
+pub struct V6StripZone {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    addr: Option<Ipv6Addr>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    zone: Option<String>,
+    had_zone: bool,
+}
+
+/// Splits a scoped address such as `fe80::1%eth0` into the address and its
+/// zone ID.
+pub async fn v6_strip_zone(params: Query<V6StripZoneParameters>) -> impl IntoResponse {
+    let (addr, zone) = match params.addr.split_once('%') {
+        Some((_, "")) => return (StatusCode::BAD_REQUEST, "Empty zone ID").into_response(),
+        Some((addr, zone)) => (addr, Some(zone)),
+        None => (params.addr.as_str(), None),
+    };
+    let Ok(addr) = addr.parse::<Ipv6Addr>() else {
+        return (StatusCode::BAD_REQUEST, "Invalid IPv6 address").into_response();
+    };
+
+    Json(match zone {
+        Some(zone) => V6StripZone {
+            addr: Some(addr),
+            zone: Some(zone.to_string()),
+            had_zone: true,
+        },
+        None => V6StripZone {
+            addr: None,
+            zone: None,
+            had_zone: false,
+        },
+    })
+    .into_response()
+}
+
+#[derive(serde::Deserialize)]
+pub struct V6MacParameters {
+    addr: Ipv6Addr,
+}
+
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct Eui64Mac {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mac: Option<String>,
+    eui64: bool,
+}
+
+/// Recovers the MAC address from a modified EUI-64 interface identifier
+/// (RFC 4291 appendix A), if the address has one.
+fn eui64_mac(addr: Ipv6Addr) -> Option<[u8; 6]> {
+    let [.., a, b, c, d, e, f, g, h] = addr.octets();
+    let universal = a & 0x02 != 0;
+    if !universal || [d, e] != [0xff, 0xfe] {
+        return None;
+    }
+
+    Some([a ^ 0x02, b, c, f, g, h])
+}
+
+pub async fn v6_mac(params: Query<V6MacParameters>) -> impl IntoResponse {
+    let mac = eui64_mac(params.addr).map(|mac| {
+        mac.iter()
+            .map(|b| format!("{b:02x}"))
+            .collect::<Vec<_>>()
+            .join(":")
+    });
+
+    Json(Eui64Mac {
+        eui64: mac.is_some(),
+        mac,
+    })
+}
+
+#[derive(serde::Deserialize)]
+pub struct PingParameters {
+    addr: Ipv4Addr,
+}
+
+#[derive(Debug, PartialEq, serde::Serialize)]
+pub struct PingResult {
+    addr: Ipv4Addr,
+    reachable: bool,
+    reason: &'static str,
+}
+
+/// Stub that always reports unreachable, since sending ICMP requires raw
+/// sockets. The response shape is the one a real implementation will return.
+pub async fn ping(params: Query<PingParameters>) -> Json<PingResult> {
+    Json(PingResult {
+        addr: params.addr,
+        reachable: false,
+        reason: "icmp-requires-elevated-privileges",
+    })
+}
+
+#[derive(serde::Deserialize)]
+pub struct ObfuscateParameters {
+    addr: Ipv4Addr,
+}
+
+fn obfuscation_key() -> Result<Ipv4Addr, &'static str> {
+    std::env::var("OBFUSCATION_KEY")
+        .map_err(|_| "OBFUSCATION_KEY is not set")?
+        .parse()
+        .map_err(|_| "OBFUSCATION_KEY is not an IPv4 address")
+}
+
+fn obfuscate_response(addr: Ipv4Addr) -> Response {
+    match obfuscation_key() {
+        Ok(key) => xor_v4(addr, key).to_string().into_response(),
+        Err(err) => (StatusCode::BAD_REQUEST, err).into_response(),
+    }
+}
+
+pub async fn obfuscate(params: Query<ObfuscateParameters>) -> impl IntoResponse {
+    obfuscate_response(params.addr)
+}
+
+/// XOR is its own inverse, so this is `obfuscate` under another name.
+pub async fn deobfuscate(params: Query<ObfuscateParameters>) -> impl IntoResponse {
+    obfuscate_response(params.addr)
+}
+
+#[derive(serde::Deserialize)]
+pub struct ArpaParameters {
+    addr: String,
+}
+
+#[derive(serde::Serialize)]
+pub struct ArpaName {
+    arpa: String,
+}
+
+fn v4_arpa(addr: Ipv4Addr) -> String {
+    let [a, b, c, d] = addr.octets();
+    format!("{d}.{c}.{b}.{a}.in-addr.arpa")
+}
+
+fn v6_arpa_name(addr: Ipv6Addr) -> String {
+    let nibbles = format!("{:032x}", u128::from(addr));
+    let reversed = nibbles.chars().rev().map(String::from).collect::<Vec<_>>();
+    format!("{}.ip6.arpa", reversed.join("."))
+}
+
+pub async fn arpa(params: Query<ArpaParameters>) -> impl IntoResponse {
+    match params.addr.parse() {
+        Ok(addr) => Json(ArpaName {
+            arpa: v4_arpa(addr),
+        })
+        .into_response(),
+        Err(_) => (StatusCode::BAD_REQUEST, "Invalid IPv4 address").into_response(),
+    }
+}
+
+pub async fn v6_arpa(params: Query<ArpaParameters>) -> impl IntoResponse {
+    match params.addr.parse() {
+        Ok(addr) => Json(ArpaName {
+            arpa: v6_arpa_name(addr),
+        })
+        .into_response(),
+        Err(_) => (StatusCode::BAD_REQUEST, "Invalid IPv6 address").into_response(),
+    }
+}
+
+#[derive(serde::Deserialize)]
+pub struct MaskParameters {
+    addr: Ipv4Addr,
+    mask: Ipv4Addr,
+}
+
+#[derive(serde::Serialize)]
+pub struct MaskedAddress {
+    network: Ipv4Addr,
+    host: Ipv4Addr,
+}
+
+/// A subnet mask's ones must all come before its zeros.
+fn is_contiguous_mask(mask: u32) -> bool {
+    mask.leading_ones() + mask.trailing_zeros() == 32
+}
+
+pub async fn mask(params: Query<MaskParameters>) -> impl IntoResponse {
+    let mask = u32::from(params.mask);
+    if !is_contiguous_mask(mask) {
+        return (StatusCode::BAD_REQUEST, "Not a valid subnet mask").into_response();
+    }
+
+    let addr = u32::from(params.addr);
+    Json(MaskedAddress {
+        network: Ipv4Addr::from(addr & mask),
+        host: Ipv4Addr::from(addr & !mask),
+    })
+    .into_response()
+}
+
+#[derive(serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BroadcastType {
+    Directed,
+    Limited,
+    None,
+}
+
+#[derive(serde::Serialize)]
+pub struct Broadcast {
+    is_broadcast: bool,
+    #[serde(rename = "type")]
+    kind: BroadcastType,
+}
+
+fn broadcast_type(addr: Ipv4Addr, mask: u32) -> BroadcastType {
+    let host = u32::from(addr) & !mask;
+    if addr == Ipv4Addr::BROADCAST {
+        BroadcastType::Limited
+    } else if mask.trailing_zeros() >= 2 && host == !mask {
+        // /31 and /32 have no room for a broadcast address (RFC 3021).
+        BroadcastType::Directed
+    } else {
+        BroadcastType::None
+    }
+}
+
+pub async fn is_broadcast(params: Query<MaskParameters>) -> impl IntoResponse {
+    let mask = u32::from(params.mask);
+    if !is_contiguous_mask(mask) {
+        return (StatusCode::BAD_REQUEST, "Not a valid subnet mask").into_response();
+    }
+
+    let kind = broadcast_type(params.addr, mask);
+    Json(Broadcast {
+        is_broadcast: !matches!(kind, BroadcastType::None),
+        kind,
+    })
+    .into_response()
+}
+
+#[derive(serde::Deserialize)]
+pub struct V6FromSeedParameters {
+    seed: String,
+    prefix: String,
+}
+
+/// Derives a stable interface identifier from the seed's SHA-256 digest.
+fn v6_from_seed(seed: &str, prefix: u128) -> Ipv6Addr {
+    let digest = Sha256::digest(seed.as_bytes());
+    let mut iid = [0; 8];
+    iid.copy_from_slice(&digest[..8]);
+    // Clear the universal/local bit: this identifier isn't derived from a MAC.
+    iid[0] &= !0x02;
+
+    Ipv6Addr::from(prefix | u128::from(u64::from_be_bytes(iid)))
+}
+
+pub async fn v6_from_seed_addr(params: Query<V6FromSeedParameters>) -> impl IntoResponse {
+    match parse_v6_cidr(&params.prefix) {
+        Ok((prefix, 64)) => v6_from_seed(&params.seed, prefix)
+            .to_string()
+            .into_response(),
+        Ok(_) => (StatusCode::BAD_REQUEST, "Prefix length must be 64").into_response(),
+        Err(err) => (StatusCode::BAD_REQUEST, err).into_response(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn key_then_its_negation_restores_the_address() {
+        let start = Ipv4Addr::new(10, 0, 0, 1);
+        let key = Ipv4Addr::new(1, 2, 250, 128);
+        let negated = Ipv4Addr::from(key.octets().map(u8::wrapping_neg));
+
+        assert_eq!([key, negated].into_iter().fold(start, apply_key), start);
+    }
+
+    #[test]
+    fn key_then_its_bitwise_complement_decrements_each_octet() {
+        // k + !k is 255 in every octet, which wraps around to subtracting one.
+        let start = Ipv4Addr::new(10, 0, 0, 1);
+        let key = Ipv4Addr::new(1, 2, 250, 128);
+        let complement = Ipv4Addr::from(!u32::from(key));
+
+        assert_eq!(
+            [key, complement].into_iter().fold(start, apply_key),
+            Ipv4Addr::new(9, 255, 255, 0)
+        );
+    }
+
+    fn addrs(addrs: &[&str]) -> Vec<String> {
+        addrs.iter().map(|addr| addr.to_string()).collect()
+    }
+
+    #[test]
+    fn sorts_v6_addresses_numerically() {
+        let input = addrs(&["ffff::", "2001:db8::", "::1", "fe80::", "2001:db8::1"]);
+        let expected = ["::1", "2001:db8::", "2001:db8::1", "fe80::", "ffff::"]
+            .map(|addr| addr.parse::<Ipv6Addr>().unwrap());
+
+        assert_eq!(sort_v6(&input, &SortOrder::Asc).unwrap(), expected);
+
+        let mut descending = expected.to_vec();
+        descending.reverse();
+        assert_eq!(sort_v6(&input, &SortOrder::Desc).unwrap(), descending);
+    }
+
+    #[test]
+    fn reports_first_invalid_v6_address() {
+        let input = addrs(&["::1", "not-an-address", "10.0.0.1"]);
+
+        assert_eq!(
+            sort_v6(&input, &SortOrder::Asc).unwrap_err(),
+            "Invalid IPv6 address: not-an-address"
+        );
+    }
+
+    #[test]
+    fn fragments_payload_across_mtu() {
+        let fragments = (0..3)
+            .map(|fragment_index| {
+                fragment(&FragmentParameters {
+                    payload_size: 3000,
+                    mtu: 1500,
+                    fragment_index,
+                })
+                .unwrap()
+            })
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            fragments,
+            [
+                Fragment {
+                    offset: 0,
+                    size: 1480,
+                    more_fragments: true,
+                },
+                Fragment {
+                    offset: 185,
+                    size: 1480,
+                    more_fragments: true,
+                },
+                Fragment {
+                    offset: 370,
+                    size: 40,
+                    more_fragments: false,
+                },
+            ]
+        );
+        assert!(
+            fragment(&FragmentParameters {
+                payload_size: 3000,
+                mtu: 1500,
+                fragment_index: 3,
+            })
+            .is_err()
+        );
+    }
+
+    #[test]
+    fn classifies_unique_local_addresses() {
+        let classify = |addr: &str| classify_ula(addr.parse().unwrap());
+
+        assert_eq!(
+            classify("fc00::1"),
+            UlaClassification {
+                is_ula: true,
+                locally_assigned: false,
+            }
+        );
+        assert_eq!(
+            classify("fd00::1"),
+            UlaClassification {
+                is_ula: true,
+                locally_assigned: true,
+            }
+        );
+        for addr in ["fe80::1", "2001:4860:4860::8888"] {
+            assert_eq!(
+                classify(addr),
+                UlaClassification {
+                    is_ula: false,
+                    locally_assigned: false,
+                }
+            );
+        }
+    }
+
+    #[test]
+    fn rotates_addresses_by_whole_octets() {
+        let addr = Ipv4Addr::new(192, 168, 1, 42);
+
+        assert_eq!(rotate_addr(addr, 8), Ipv4Addr::new(168, 1, 42, 192));
+        assert_eq!(rotate_addr(addr, -8), Ipv4Addr::new(42, 192, 168, 1));
+        assert_eq!(rotate_addr(addr, 16), Ipv4Addr::new(1, 42, 192, 168));
+        assert_eq!(rotate_addr(addr, -16), Ipv4Addr::new(1, 42, 192, 168));
+        assert_eq!(rotate_addr(addr, 0), addr);
+    }
+
+    fn cidr(value: &str) -> Ipv4Cidr {
+        Ipv4Cidr::try_from(value.to_string()).unwrap()
+    }
+
+    #[test]
+    fn default_route_has_no_parent_or_sibling() {
+        assert_eq!(
+            subnet_hierarchy(cidr("0.0.0.0/0")),
+            SubnetHierarchy {
+                parent: None,
+                sibling: None,
+                children: Some([cidr("0.0.0.0/1"), cidr("128.0.0.0/1")]),
+            }
+        );
+    }
+
+    #[test]
+    fn host_route_has_no_children() {
+        assert_eq!(
+            subnet_hierarchy(cidr("10.0.0.7/32")),
+            SubnetHierarchy {
+                parent: Some(cidr("10.0.0.6/31")),
+                sibling: Some(cidr("10.0.0.6/32")),
+                children: None,
+            }
+        );
+    }
+
+    #[test]
+    fn intermediate_prefix_has_parent_sibling_and_children() {
+        assert_eq!(
+            subnet_hierarchy(cidr("192.168.1.0/24")),
+            SubnetHierarchy {
+                parent: Some(cidr("192.168.0.0/23")),
+                sibling: Some(cidr("192.168.0.0/24")),
+                children: Some([cidr("192.168.1.0/25"), cidr("192.168.1.128/25")]),
+            }
+        );
+        assert_eq!(
+            subnet_hierarchy(cidr("10.1.2.3/8")),
+            SubnetHierarchy {
+                parent: Some(cidr("10.0.0.0/7")),
+                sibling: Some(cidr("11.0.0.0/8")),
+                children: Some([cidr("10.0.0.0/9"), cidr("10.128.0.0/9")]),
+            }
+        );
+    }
+
+    #[test]
+    fn hierarchy_serializes_cidrs_as_strings() {
+        let json = serde_json::to_value(subnet_hierarchy(cidr("0.0.0.0/0"))).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "parent": null,
+                "sibling": null,
+                "children": ["0.0.0.0/1", "128.0.0.0/1"],
+            })
+        );
+    }
+
+    #[test]
+    fn v6_add_wraps_past_the_last_address() {
+        let last_but_one = "ffff:ffff:ffff:ffff:ffff:ffff:ffff:fffe".parse().unwrap();
+
+        assert_eq!(
+            add_v6(last_but_one, 1, false),
+            Some(Ipv6Addr::from(u128::MAX))
+        );
+        assert_eq!(add_v6(last_but_one, 2, true), Some(Ipv6Addr::UNSPECIFIED));
+        assert_eq!(add_v6(last_but_one, 3, true), Some(Ipv6Addr::from(1)));
+        assert_eq!(add_v6(last_but_one, 2, false), None);
+    }
+
+    #[test]
+    fn v6_add_carries_across_segments() {
+        let from = "2001:db8::ffff:ffff:ffff:ffff".parse().unwrap();
+
+        assert_eq!(
+            add_v6(from, 1, false),
+            Some("2001:db8:0:1::".parse().unwrap())
+        );
+        assert_eq!(
+            add_v6(Ipv6Addr::UNSPECIFIED, u64::MAX, false),
+            Some("::ffff:ffff:ffff:ffff".parse().unwrap())
+        );
+    }
+
+    #[tokio::test]
+    async fn v6_add_rejects_overflow_unless_wrapping() {
+        let query = |q: &str| Query::try_from_uri(&format!("/?{q}").parse().unwrap()).unwrap();
+        let from = "from=ffff:ffff:ffff:ffff:ffff:ffff:ffff:fffe&n=2";
+
+        let wrapped = v6_add(query(&format!("{from}&wrap=true")))
+            .await
+            .into_response();
+        assert_eq!(wrapped.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(wrapped.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert_eq!(body, "::");
+
+        let default = v6_add(query(from)).await.into_response();
+        assert_eq!(default.status(), StatusCode::OK);
+
+        let rejected = v6_add(query(&format!("{from}&wrap=false")))
+            .await
+            .into_response();
+        assert_eq!(rejected.status(), StatusCode::UNPROCESSABLE_ENTITY);
+    }
+
+    #[test]
+    fn extracts_mac_from_eui64_interface_id() {
+        // fe80::21a:2bff:fe3c:4d5e is the link-local address SLAAC derives
+        // from 00:1a:2b:3c:4d:5e.
+        let addr = "fe80::21a:2bff:fe3c:4d5e".parse().unwrap();
+        assert_eq!(eui64_mac(addr), Some([0x00, 0x1a, 0x2b, 0x3c, 0x4d, 0x5e]));
+    }
+
+    #[test]
+    fn ignores_non_eui64_interface_ids() {
+        // Missing the ff:fe filler.
+        assert_eq!(eui64_mac("fe80::21a:2bff:fd3c:4d5e".parse().unwrap()), None);
+        // Universal/local bit clear, as in privacy addresses.
+        assert_eq!(eui64_mac("fe80::1a:2bff:fe3c:4d5e".parse().unwrap()), None);
+    }
+
+    #[tokio::test]
+    async fn ping_always_reports_unreachable() {
+        let params = Query(PingParameters {
+            addr: Ipv4Addr::new(192, 0, 2, 1),
+        });
+
+        let Json(result) = ping(params).await;
+        assert_eq!(
+            result,
+            PingResult {
+                addr: Ipv4Addr::new(192, 0, 2, 1),
+                reachable: false,
+                reason: "icmp-requires-elevated-privileges",
+            }
+        );
+    }
+
+    #[test]
+    fn deobfuscating_restores_the_address() {
+        let key = Ipv4Addr::new(172, 31, 200, 9);
+        for addr in [
+            Ipv4Addr::new(10, 0, 0, 1),
+            Ipv4Addr::new(255, 255, 255, 255),
+            Ipv4Addr::UNSPECIFIED,
+        ] {
+            let obfuscated = xor_v4(addr, key);
+            assert_ne!(obfuscated, addr);
+            assert_eq!(xor_v4(obfuscated, key), addr);
+        }
+    }
+
+    #[test]
+    fn v6_bitwise_operations_satisfy_de_morgan() {
+        let not = |addr| xor_v6(addr, Ipv6Addr::from(u128::MAX));
+        let pairs: [(Ipv6Addr, Ipv6Addr); 3] = [
+            (
+                "2001:db8::1".parse().unwrap(),
+                "ffff:ffff::".parse().unwrap(),
+            ),
+            (
+                "fe80::1:2:3:4".parse().unwrap(),
+                "::ffff:ffff:ffff:ffff".parse().unwrap(),
+            ),
+            (Ipv6Addr::UNSPECIFIED, Ipv6Addr::from(u128::MAX)),
+        ];
+
+        for (a, b) in pairs {
+            assert_eq!(not(and_v6(a, b)), or_v6(not(a), not(b)));
+            assert_eq!(not(or_v6(a, b)), and_v6(not(a), not(b)));
+            // XOR is the part of OR that AND doesn't cover.
+            assert_eq!(xor_v6(a, b), and_v6(or_v6(a, b), not(and_v6(a, b))));
+        }
+    }
+
+    #[test]
+    fn v6_and_or_split_network_and_host() {
+        let addr = "2001:db8:1:2:aaaa:bbbb:cccc:dddd".parse().unwrap();
+        let prefix_mask = "ffff:ffff:ffff:ffff::".parse().unwrap();
+        let host_mask = "::ffff:ffff:ffff:ffff".parse().unwrap();
+
+        assert_eq!(
+            and_v6(addr, prefix_mask),
+            "2001:db8:1:2::".parse::<Ipv6Addr>().unwrap()
+        );
+        assert_eq!(
+            or_v6(and_v6(addr, prefix_mask), host_mask),
+            "2001:db8:1:2:ffff:ffff:ffff:ffff"
+                .parse::<Ipv6Addr>()
+                .unwrap()
+        );
+    }
+
+    #[test]
+    fn merges_sibling_halves_into_their_parent() {
+        assert_eq!(
+            merge_subnets(cidr("10.0.1.0/24"), cidr("10.0.0.0/24")),
+            Ok(cidr("10.0.0.0/23"))
+        );
+        assert_eq!(
+            merge_subnets(cidr("0.0.0.0/1"), cidr("128.0.0.0/1")),
+            Ok(cidr("0.0.0.0/0"))
+        );
+    }
+
+    #[test]
+    fn refuses_to_merge_subnets_of_different_sizes() {
+        let err = merge_subnets(cidr("10.0.0.0/24"), cidr("10.0.1.0/25")).unwrap_err();
+        assert!(err.contains("different sizes"), "{err}");
+    }
+
+    #[test]
+    fn refuses_to_merge_non_adjacent_subnets() {
+        let err = merge_subnets(cidr("10.0.0.0/24"), cidr("10.0.2.0/24")).unwrap_err();
+        assert!(err.contains("not adjacent"), "{err}");
+    }
+
+    #[test]
+    fn refuses_to_merge_adjacent_subnets_with_different_parents() {
+        // Adjacent, but 10.0.1.0/24 is the upper half of 10.0.0.0/23 and
+        // 10.0.2.0/24 the lower half of 10.0.2.0/23.
+        let err = merge_subnets(cidr("10.0.1.0/24"), cidr("10.0.2.0/24")).unwrap_err();
+        assert!(err.contains("different /23 supernets"), "{err}");
+    }
+
+    #[test]
+    fn different_seeds_give_different_addresses() {
+        let prefix = u128::from("2001:db8::".parse::<Ipv6Addr>().unwrap());
+
+        assert_eq!(v6_from_seed("elf", prefix), v6_from_seed("elf", prefix));
+        assert_ne!(
+            v6_from_seed("elf", prefix),
+            v6_from_seed("reindeer", prefix)
+        );
+    }
+
+    #[test]
+    fn deaggregates_into_equal_sub_prefixes() {
+        let prefixes = deaggregate_prefix(cidr("10.0.0.0/24"), 26, DEAGGREGATE_LIMIT).unwrap();
+        assert_eq!(
+            prefixes,
+            [
+                "10.0.0.0/26",
+                "10.0.0.64/26",
+                "10.0.0.128/26",
+                "10.0.0.192/26"
+            ]
+            .map(cidr)
+        );
+
+        assert!(deaggregate_prefix(cidr("10.0.0.0/24"), 24, DEAGGREGATE_LIMIT).is_err());
+        assert!(deaggregate_prefix(cidr("10.0.0.0/8"), 20, DEAGGREGATE_LIMIT).is_err());
+        assert!(deaggregate_prefix(cidr("10.0.0.0/8"), 20, DEAGGREGATE_LARGE_LIMIT).is_ok());
+    }
+
+    #[test]
+    fn siit_round_trips_through_the_well_known_prefix() {
+        let prefix = parse_nat64_prefix("64:ff9b::/96").unwrap();
+        let v4 = Ipv4Addr::new(192, 0, 2, 33);
+        let v6 = "64:ff9b::c000:221".parse::<Ipv6Addr>().unwrap();
+
+        assert_eq!(embed_v4(prefix, v4), v6);
+        assert_eq!(extract_v4(prefix, v6), Some(v4));
+        assert_eq!(
+            extract_v4(prefix, "2001:db8::c000:221".parse().unwrap()),
+            None
+        );
+    }
+
+    #[tokio::test]
+    async fn siit_extract_rejects_addresses_outside_the_prefix() {
+        let query = |q: &str| Query::try_from_uri(&format!("/?{q}").parse().unwrap()).unwrap();
+
+        let outside = v6_siit_extract(query("ipv6=2001:db8::1&prefix=64:ff9b::/96"))
+            .await
+            .into_response();
+        assert_eq!(outside.status(), StatusCode::BAD_REQUEST);
+
+        let inside = v6_siit_extract(query("ipv6=64:ff9b::c000:221&prefix=64:ff9b::/96"))
+            .await
+            .into_response();
+        assert_eq!(inside.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn ecmp_selection_is_stable_per_destination() {
+        let paths = [(1, 1), (2, 3), (3, 0)].map(|(last, weight)| EcmpPath {
+            nexthop: Ipv4Addr::new(192, 0, 2, last),
+            weight,
+        });
+
+        for last in 0..=255 {
+            let addr = Ipv4Addr::new(10, 0, 0, last);
+            let selection = select_path(addr, &paths).unwrap();
+            assert_eq!(selection, select_path(addr, &paths).unwrap());
+            assert_ne!(selection.path_index, 2);
+        }
+        assert!(select_path(Ipv4Addr::LOCALHOST, &[]).is_err());
+    }
+
+    #[test]
+    fn reversing_twice_restores_the_address() {
+        assert_eq!(
+            reverse_v4(Ipv4Addr::new(192, 168, 1, 42)),
+            Ipv4Addr::new(42, 1, 168, 192)
+        );
+
+        for seed in 0..1000 {
+            let v4 = Ipv4Addr::from(mix32(seed));
+            assert_eq!(reverse_v4(reverse_v4(v4)), v4);
+
+            let v6 = Ipv6Addr::from(
+                (0..4).fold(0u128, |acc, i| acc << 32 | u128::from(mix32(seed * 4 + i))),
+            );
+            assert_eq!(reverse_v6(reverse_v6(v6)), v6);
+        }
+    }
+
+    #[test]
+    fn v6_arpa_name_reverses_every_nibble() {
+        assert_eq!(
+            v6_arpa_name("2001:db8::1".parse().unwrap()),
+            "1.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.0.8.b.d.0.1.0.0.2.ip6.arpa"
+        );
+    }
+}